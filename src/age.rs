@@ -0,0 +1,87 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+const WEEK: i64 = 7 * DAY;
+const MONTH: i64 = 30 * DAY;
+const YEAR: i64 = 365 * DAY;
+
+/// Seconds since the Unix epoch.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Compact relative age: `45s`, `12m`, `3h`, `5d`, `7w`, `4mo`, `2y`.
+pub fn format(seconds: i64) -> String {
+    let s = seconds.max(0);
+    match s {
+        _ if s < MINUTE => format!("{s}s"),
+        _ if s < HOUR => format!("{}m", s / MINUTE),
+        _ if s < DAY => format!("{}h", s / HOUR),
+        _ if s < WEEK => format!("{}d", s / DAY),
+        _ if s < MONTH => format!("{}w", s / WEEK),
+        _ if s < YEAR => format!("{}mo", s / MONTH),
+        _ => format!("{}y", s / YEAR),
+    }
+}
+
+/// Parses an age in the units `format` prints, e.g. `30d` or `2w`, into
+/// seconds. Used as a clap value parser.
+pub fn parse(s: &str) -> Result<i64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("'{s}' has no unit (use s, m, h, d, w, mo or y)"))?;
+    let (number, unit) = s.split_at(split);
+    let number: i64 = number
+        .parse()
+        .map_err(|_| format!("'{s}' does not start with a number"))?;
+    let unit = match unit {
+        "s" => 1,
+        "m" => MINUTE,
+        "h" => HOUR,
+        "d" => DAY,
+        "w" => WEEK,
+        "mo" => MONTH,
+        "y" => YEAR,
+        other => {
+            return Err(format!(
+                "unknown unit '{other}' (use s, m, h, d, w, mo or y)"
+            ));
+        }
+    };
+    Ok(number.saturating_mul(unit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting() {
+        assert_eq!(format(-5), "0s");
+        assert_eq!(format(59), "59s");
+        assert_eq!(format(60 * 12), "12m");
+        assert_eq!(format(3600 * 3), "3h");
+        assert_eq!(format(86400 * 5), "5d");
+        assert_eq!(format(86400 * 14), "2w");
+        assert_eq!(format(86400 * 120), "4mo");
+        assert_eq!(format(86400 * 800), "2y");
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse("45s"), Ok(45));
+        assert_eq!(parse("12h"), Ok(12 * HOUR));
+        assert_eq!(parse("30d"), Ok(30 * DAY));
+        assert_eq!(parse("2w"), Ok(2 * WEEK));
+        assert_eq!(parse("4mo"), Ok(4 * MONTH));
+        assert_eq!(parse("1y"), Ok(YEAR));
+        assert!(parse("30").is_err());
+        assert!(parse("d").is_err());
+        assert!(parse("3x").is_err());
+    }
+}
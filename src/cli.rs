@@ -1,6 +1,10 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+
+use crate::age;
+use crate::config::Column;
+use crate::output::OutputFormat;
 
 #[derive(Parser, Debug)]
 #[command(name = "arvore", about = "A fast git worktree manager", version)]
@@ -8,6 +12,15 @@ pub struct Cli {
     #[arg(long, global = true, help = "Path to config file")]
     pub config: Option<PathBuf>,
 
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format"
+    )]
+    pub format: OutputFormat,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -16,35 +29,121 @@ pub struct Cli {
 pub enum Commands {
     #[command(about = "Create a new worktree")]
     Create {
-        branch: String,
+        #[arg(required_unless_present = "pr")]
+        branch: Option<String>,
         #[arg(long, help = "Base ref to branch from")]
         from: Option<String>,
+        #[arg(
+            long,
+            value_name = "NUMBER",
+            conflicts_with = "from",
+            help = "Check out a pull/merge request"
+        )]
+        pr: Option<u64>,
+        #[arg(
+            long,
+            conflicts_with_all = ["from", "pr"],
+            help = "Remote to check the branch out from"
+        )]
+        remote: Option<String>,
         #[arg(long, help = "Open worktree after creation")]
         open: bool,
+        #[arg(long, help = "Skip post_create hooks")]
+        no_hooks: bool,
+    },
+
+    #[command(about = "Create a worktree for a pull/merge request")]
+    Review {
+        number: u64,
+        #[arg(long, help = "Open worktree after creation")]
+        open: bool,
+        #[arg(long, help = "Skip post_create hooks")]
+        no_hooks: bool,
     },
 
     #[command(name = "ls", about = "List all worktrees")]
     List {
         #[arg(long, help = "Machine-readable output")]
         porcelain: bool,
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            help = "Columns to show, comma-separated"
+        )]
+        columns: Option<Vec<Column>>,
+        #[arg(
+            long,
+            help = "List every repository with worktrees under worktree_base"
+        )]
+        all_repos: bool,
     },
 
     #[command(name = "rm", about = "Remove a worktree")]
     Remove {
         target: String,
-        #[arg(long, help = "Force removal even if dirty")]
-        force: bool,
+        #[arg(
+            long,
+            short = 'f',
+            action = ArgAction::Count,
+            help = "Force removal even if dirty; twice to include locked worktrees"
+        )]
+        force: u8,
+        #[arg(long, help = "Remove even if a pre_remove hook fails")]
+        ignore_hook_failure: bool,
+        #[arg(long, help = "Skip pre_remove/post_remove hooks")]
+        no_hooks: bool,
+    },
+
+    #[command(name = "mv", about = "Rename a worktree's branch and move it to match")]
+    Rename {
+        #[arg(help = "Branch or path of the worktree")]
+        old: String,
+        #[arg(help = "New branch name")]
+        new: String,
+        #[arg(
+            long,
+            help = "Track the new name on the remote, or drop the stale upstream"
+        )]
+        set_upstream: bool,
+        #[arg(long, help = "Skip post_rename hooks")]
+        no_hooks: bool,
+    },
+
+    #[command(about = "Lock a worktree so rm, clean and git prune leave it alone")]
+    Lock {
+        #[arg(help = "Branch or path of the worktree")]
+        target: String,
+        #[arg(long, help = "Why it is locked, shown by ls")]
+        reason: Option<String>,
+    },
+
+    #[command(about = "Unlock a worktree")]
+    Unlock {
+        #[arg(help = "Branch or path of the worktree")]
+        target: String,
     },
 
     #[command(about = "Open a worktree in editor/terminal")]
     Open {
         branch: String,
-        #[arg(long, help = "Open in Cursor")]
-        cursor: bool,
-        #[arg(long, help = "Open in Warp")]
-        warp: bool,
-        #[arg(long, help = "Open in both Warp and Cursor")]
-        all: bool,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            help = "Openers to use, comma-separated (default: open_with from config)"
+        )]
+        with: Option<Vec<String>>,
+    },
+
+    #[command(
+        visible_alias = "sw",
+        about = "Pick a worktree with a fuzzy finder and print its path"
+    )]
+    Switch {
+        #[arg(help = "Branch, directory or partial name to match")]
+        query: Option<String>,
+        #[arg(long, help = "Open the worktree instead of printing its path")]
+        open: bool,
     },
 
     #[command(about = "Print worktree path for a branch")]
@@ -52,12 +151,126 @@ pub enum Commands {
 
     #[command(about = "Clean up merged/stale worktrees")]
     Clean {
+        #[arg(long, help = "Branch merges are checked against (default: detected)")]
+        base: Option<String>,
         #[arg(long, help = "List candidates without removing")]
         dry_run: bool,
+        #[arg(
+            long,
+            short = 'f',
+            action = ArgAction::Count,
+            help = "Remove dirty worktrees even if clean.dirty would keep them; twice to include locked worktrees"
+        )]
+        force: u8,
+        #[arg(long, help = "Remove even if a pre_remove hook fails")]
+        ignore_hook_failure: bool,
+        #[arg(long, help = "Skip pre_remove/post_remove hooks")]
+        no_hooks: bool,
+        #[arg(
+            long,
+            short = 'y',
+            visible_alias = "all",
+            conflicts_with = "dry_run",
+            help = "Remove every candidate without prompting"
+        )]
+        yes: bool,
+        #[arg(
+            long,
+            conflicts_with = "remote_deleted_only",
+            help = "Only consider branches merged into the base"
+        )]
+        merged_only: bool,
+        #[arg(long, help = "Only consider branches deleted on the remote")]
+        remote_deleted_only: bool,
+        #[arg(
+            long,
+            value_name = "AGE",
+            value_parser = age::parse,
+            help = "Only consider worktrees whose last commit is older than AGE (e.g. 30d, 2w)"
+        )]
+        older_than: Option<i64>,
+        #[arg(long, help = "Leave worktrees with uncommitted changes alone")]
+        skip_dirty: bool,
+        #[arg(
+            long,
+            help = "Clean every repository with worktrees under worktree_base"
+        )]
+        all_repos: bool,
+    },
+
+    #[command(about = "List or empty the trash of removed worktrees")]
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommand,
+    },
+
+    #[command(about = "Bring a removed worktree back from the trash")]
+    Restore {
+        #[arg(help = "Branch or trash entry id; the latest match is restored")]
+        target: String,
+    },
+
+    #[command(about = "Find and fix stale, moved or orphaned worktrees")]
+    Doctor {
+        #[arg(long, help = "Report problems without fixing them")]
+        dry_run: bool,
+        #[arg(
+            long,
+            short = 'y',
+            conflicts_with = "dry_run",
+            help = "Fix every problem without prompting"
+        )]
+        yes: bool,
+    },
+
+    #[command(about = "Run a command in every worktree")]
+    Exec {
+        #[arg(
+            long,
+            value_name = "GLOB",
+            help = "Only worktrees whose branch matches GLOB (e.g. 'feature/*')"
+        )]
+        branches: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "clean",
+            help = "Only worktrees with uncommitted changes"
+        )]
+        dirty: bool,
+        #[arg(long, help = "Only worktrees without uncommitted changes")]
+        clean: bool,
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 1,
+            help = "Run in up to N worktrees at once"
+        )]
+        parallel: usize,
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
     },
 
     #[command(about = "Generate shell completions")]
     Completions { shell: ShellType },
+
+    #[command(about = "Print a shell function that lets arvore change directory")]
+    ShellInit { shell: ShellType },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TrashCommand {
+    #[command(about = "List removed worktrees of this repository")]
+    Ls,
+    #[command(about = "Delete removed worktrees for good")]
+    Purge {
+        #[arg(
+            long,
+            value_name = "AGE",
+            value_parser = age::parse,
+            help = "Only delete entries removed longer than AGE ago (e.g. 30d, 2w)"
+        )]
+        older_than: Option<i64>,
+    },
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -91,10 +304,20 @@ mod tests {
     fn parse_create_basic() {
         let cli = Cli::try_parse_from(["arvore", "create", "my-branch"]).unwrap();
         match cli.command {
-            Commands::Create { branch, from, open } => {
-                assert_eq!(branch, "my-branch");
+            Commands::Create {
+                branch,
+                from,
+                pr,
+                remote,
+                open,
+                no_hooks,
+            } => {
+                assert_eq!(branch.as_deref(), Some("my-branch"));
                 assert!(from.is_none());
+                assert!(pr.is_none());
+                assert!(remote.is_none());
                 assert!(!open);
+                assert!(!no_hooks);
             }
             _ => panic!("expected Create"),
         }
@@ -118,11 +341,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_create_no_hooks() {
+        let cli = Cli::try_parse_from(["arvore", "create", "my-branch", "--no-hooks"]).unwrap();
+        match cli.command {
+            Commands::Create { no_hooks, .. } => assert!(no_hooks),
+            _ => panic!("expected Create"),
+        }
+    }
+
+    #[test]
+    fn parse_create_pr_without_branch() {
+        let cli = Cli::try_parse_from(["arvore", "create", "--pr", "1234"]).unwrap();
+        match cli.command {
+            Commands::Create { branch, pr, .. } => {
+                assert!(branch.is_none());
+                assert_eq!(pr, Some(1234));
+            }
+            _ => panic!("expected Create"),
+        }
+    }
+
+    #[test]
+    fn parse_create_requires_branch_or_pr() {
+        assert!(Cli::try_parse_from(["arvore", "create"]).is_err());
+        assert!(Cli::try_parse_from(["arvore", "create", "--pr", "1", "--from", "main"]).is_err());
+    }
+
+    #[test]
+    fn parse_create_with_remote() {
+        let cli = Cli::try_parse_from(["arvore", "create", "fix", "--remote", "upstream"]).unwrap();
+        match cli.command {
+            Commands::Create { remote, .. } => assert_eq!(remote.as_deref(), Some("upstream")),
+            _ => panic!("expected Create"),
+        }
+    }
+
+    #[test]
+    fn parse_review() {
+        let cli = Cli::try_parse_from(["arvore", "review", "42", "--open"]).unwrap();
+        match cli.command {
+            Commands::Review { number, open, .. } => {
+                assert_eq!(number, 42);
+                assert!(open);
+            }
+            _ => panic!("expected Review"),
+        }
+    }
+
     #[test]
     fn parse_list() {
         let cli = Cli::try_parse_from(["arvore", "ls"]).unwrap();
         match cli.command {
-            Commands::List { porcelain } => assert!(!porcelain),
+            Commands::List {
+                porcelain,
+                columns,
+                all_repos,
+            } => {
+                assert!(!porcelain);
+                assert!(columns.is_none());
+                assert!(!all_repos);
+            }
             _ => panic!("expected List"),
         }
     }
@@ -131,7 +410,19 @@ mod tests {
     fn parse_list_porcelain() {
         let cli = Cli::try_parse_from(["arvore", "ls", "--porcelain"]).unwrap();
         match cli.command {
-            Commands::List { porcelain } => assert!(porcelain),
+            Commands::List { porcelain, .. } => assert!(porcelain),
+            _ => panic!("expected List"),
+        }
+    }
+
+    #[test]
+    fn parse_list_columns() {
+        let cli = Cli::try_parse_from(["arvore", "ls", "--columns", "branch,main,age"]).unwrap();
+        match cli.command {
+            Commands::List { columns, .. } => assert_eq!(
+                columns,
+                Some(vec![Column::Branch, Column::Main, Column::Age])
+            ),
             _ => panic!("expected List"),
         }
     }
@@ -140,9 +431,16 @@ mod tests {
     fn parse_remove() {
         let cli = Cli::try_parse_from(["arvore", "rm", "my-branch"]).unwrap();
         match cli.command {
-            Commands::Remove { target, force } => {
+            Commands::Remove {
+                target,
+                force,
+                ignore_hook_failure,
+                no_hooks,
+            } => {
                 assert_eq!(target, "my-branch");
-                assert!(!force);
+                assert_eq!(force, 0);
+                assert!(!ignore_hook_failure);
+                assert!(!no_hooks);
             }
             _ => panic!("expected Remove"),
         }
@@ -152,34 +450,50 @@ mod tests {
     fn parse_remove_force() {
         let cli = Cli::try_parse_from(["arvore", "rm", "my-branch", "--force"]).unwrap();
         match cli.command {
-            Commands::Remove { force, .. } => assert!(force),
+            Commands::Remove { force, .. } => assert_eq!(force, 1),
+            _ => panic!("expected Remove"),
+        }
+
+        let cli = Cli::try_parse_from(["arvore", "rm", "my-branch", "-ff"]).unwrap();
+        match cli.command {
+            Commands::Remove { force, .. } => assert_eq!(force, 2),
             _ => panic!("expected Remove"),
         }
     }
 
     #[test]
-    fn parse_open_cursor() {
-        let cli = Cli::try_parse_from(["arvore", "open", "my-branch", "--cursor"]).unwrap();
+    fn parse_lock_with_reason() {
+        let cli =
+            Cli::try_parse_from(["arvore", "lock", "usb", "--reason", "on the usb drive"]).unwrap();
         match cli.command {
-            Commands::Open {
-                branch,
-                cursor,
-                all,
-                ..
-            } => {
+            Commands::Lock { target, reason } => {
+                assert_eq!(target, "usb");
+                assert_eq!(reason.as_deref(), Some("on the usb drive"));
+            }
+            _ => panic!("expected Lock"),
+        }
+    }
+
+    #[test]
+    fn parse_open_default() {
+        let cli = Cli::try_parse_from(["arvore", "open", "my-branch"]).unwrap();
+        match cli.command {
+            Commands::Open { branch, with } => {
                 assert_eq!(branch, "my-branch");
-                assert!(cursor);
-                assert!(!all);
+                assert!(with.is_none());
             }
             _ => panic!("expected Open"),
         }
     }
 
     #[test]
-    fn parse_open_all() {
-        let cli = Cli::try_parse_from(["arvore", "open", "my-branch", "--all"]).unwrap();
+    fn parse_open_with_list() {
+        let cli =
+            Cli::try_parse_from(["arvore", "open", "my-branch", "--with", "code,kitty"]).unwrap();
         match cli.command {
-            Commands::Open { all, .. } => assert!(all),
+            Commands::Open { with, .. } => {
+                assert_eq!(with.unwrap(), vec!["code", "kitty"]);
+            }
             _ => panic!("expected Open"),
         }
     }
@@ -193,11 +507,77 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_switch_without_query() {
+        let cli = Cli::try_parse_from(["arvore", "switch"]).unwrap();
+        match cli.command {
+            Commands::Switch { query, open } => {
+                assert!(query.is_none());
+                assert!(!open);
+            }
+            _ => panic!("expected Switch"),
+        }
+    }
+
+    #[test]
+    fn parse_sw_alias_with_query() {
+        let cli = Cli::try_parse_from(["arvore", "sw", "auth", "--open"]).unwrap();
+        match cli.command {
+            Commands::Switch { query, open } => {
+                assert_eq!(query.as_deref(), Some("auth"));
+                assert!(open);
+            }
+            _ => panic!("expected Switch"),
+        }
+    }
+
+    #[test]
+    fn parse_shell_init() {
+        let cli = Cli::try_parse_from(["arvore", "shell-init", "fish"]).unwrap();
+        match cli.command {
+            Commands::ShellInit { shell } => assert!(matches!(shell, ShellType::Fish)),
+            _ => panic!("expected ShellInit"),
+        }
+    }
+
     #[test]
     fn parse_clean() {
         let cli = Cli::try_parse_from(["arvore", "clean"]).unwrap();
         match cli.command {
-            Commands::Clean { dry_run } => assert!(!dry_run),
+            Commands::Clean {
+                base,
+                dry_run,
+                force,
+                ignore_hook_failure,
+                no_hooks,
+                yes,
+                merged_only,
+                remote_deleted_only,
+                older_than,
+                skip_dirty,
+                all_repos,
+            } => {
+                assert!(base.is_none());
+                assert!(!dry_run);
+                assert_eq!(force, 0);
+                assert!(!ignore_hook_failure);
+                assert!(!no_hooks);
+                assert!(!yes);
+                assert!(!merged_only);
+                assert!(!remote_deleted_only);
+                assert!(older_than.is_none());
+                assert!(!skip_dirty);
+                assert!(!all_repos);
+            }
+            _ => panic!("expected Clean"),
+        }
+    }
+
+    #[test]
+    fn parse_clean_base() {
+        let cli = Cli::try_parse_from(["arvore", "clean", "--base", "develop"]).unwrap();
+        match cli.command {
+            Commands::Clean { base, .. } => assert_eq!(base.as_deref(), Some("develop")),
             _ => panic!("expected Clean"),
         }
     }
@@ -206,11 +586,154 @@ mod tests {
     fn parse_clean_dry_run() {
         let cli = Cli::try_parse_from(["arvore", "clean", "--dry-run"]).unwrap();
         match cli.command {
-            Commands::Clean { dry_run } => assert!(dry_run),
+            Commands::Clean { dry_run, .. } => assert!(dry_run),
             _ => panic!("expected Clean"),
         }
     }
 
+    #[test]
+    fn parse_clean_force_no_hooks() {
+        let cli = Cli::try_parse_from(["arvore", "clean", "--force", "--no-hooks"]).unwrap();
+        match cli.command {
+            Commands::Clean {
+                force, no_hooks, ..
+            } => {
+                assert_eq!(force, 1);
+                assert!(no_hooks);
+            }
+            _ => panic!("expected Clean"),
+        }
+    }
+
+    #[test]
+    fn parse_clean_unattended() {
+        let cli = Cli::try_parse_from([
+            "arvore",
+            "clean",
+            "--all",
+            "--merged-only",
+            "--older-than",
+            "30d",
+            "--skip-dirty",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Clean {
+                yes,
+                merged_only,
+                older_than,
+                skip_dirty,
+                ..
+            } => {
+                assert!(yes);
+                assert!(merged_only);
+                assert_eq!(older_than, Some(30 * 86400));
+                assert!(skip_dirty);
+            }
+            _ => panic!("expected Clean"),
+        }
+    }
+
+    #[test]
+    fn parse_clean_rejects_conflicting_filters() {
+        assert!(Cli::try_parse_from(["arvore", "clean", "--older-than", "soon"]).is_err());
+        assert!(
+            Cli::try_parse_from(["arvore", "clean", "--merged-only", "--remote-deleted-only"])
+                .is_err()
+        );
+        assert!(Cli::try_parse_from(["arvore", "clean", "--yes", "--dry-run"]).is_err());
+    }
+
+    #[test]
+    fn parse_trash_purge() {
+        let cli = Cli::try_parse_from(["arvore", "trash", "purge", "--older-than", "2w"]).unwrap();
+        match cli.command {
+            Commands::Trash {
+                command: TrashCommand::Purge { older_than },
+            } => assert_eq!(older_than, Some(14 * 86400)),
+            _ => panic!("expected Trash Purge"),
+        }
+        assert!(Cli::try_parse_from(["arvore", "trash"]).is_err());
+    }
+
+    #[test]
+    fn parse_doctor() {
+        let cli = Cli::try_parse_from(["arvore", "doctor", "-y"]).unwrap();
+        match cli.command {
+            Commands::Doctor { dry_run, yes } => {
+                assert!(!dry_run);
+                assert!(yes);
+            }
+            _ => panic!("expected Doctor"),
+        }
+        assert!(Cli::try_parse_from(["arvore", "doctor", "--yes", "--dry-run"]).is_err());
+    }
+
+    #[test]
+    fn parse_exec() {
+        let cli = Cli::try_parse_from([
+            "arvore",
+            "exec",
+            "--branches",
+            "feature/*",
+            "--dirty",
+            "--parallel",
+            "4",
+            "--",
+            "cargo",
+            "test",
+            "--quiet",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Exec {
+                branches,
+                dirty,
+                clean,
+                parallel,
+                command,
+            } => {
+                assert_eq!(branches.as_deref(), Some("feature/*"));
+                assert!(dirty);
+                assert!(!clean);
+                assert_eq!(parallel, 4);
+                assert_eq!(command, ["cargo", "test", "--quiet"]);
+            }
+            _ => panic!("expected Exec"),
+        }
+        assert!(Cli::try_parse_from(["arvore", "exec"]).is_err());
+        assert!(Cli::try_parse_from(["arvore", "exec", "--dirty", "--clean", "--", "ls"]).is_err());
+    }
+
+    #[test]
+    fn parse_restore() {
+        let cli = Cli::try_parse_from(["arvore", "restore", "feature/auth"]).unwrap();
+        match cli.command {
+            Commands::Restore { target } => assert_eq!(target, "feature/auth"),
+            _ => panic!("expected Restore"),
+        }
+    }
+
+    #[test]
+    fn parse_mv() {
+        let cli = Cli::try_parse_from(["arvore", "mv", "wip", "feature/billing", "--set-upstream"])
+            .unwrap();
+        match cli.command {
+            Commands::Rename {
+                old,
+                new,
+                set_upstream,
+                no_hooks,
+            } => {
+                assert_eq!(old, "wip");
+                assert_eq!(new, "feature/billing");
+                assert!(set_upstream);
+                assert!(!no_hooks);
+            }
+            _ => panic!("expected Rename"),
+        }
+    }
+
     #[test]
     fn parse_completions_zsh() {
         let cli = Cli::try_parse_from(["arvore", "completions", "zsh"]).unwrap();
@@ -229,6 +752,20 @@ mod tests {
         assert_eq!(cli.config, Some(std::path::PathBuf::from("/custom/path")));
     }
 
+    #[test]
+    fn parse_format_defaults_to_text() {
+        let cli = Cli::try_parse_from(["arvore", "ls"]).unwrap();
+        assert_eq!(cli.format, OutputFormat::Text);
+    }
+
+    #[test]
+    fn parse_global_format_flag() {
+        let cli = Cli::try_parse_from(["arvore", "ls", "--format", "json"]).unwrap();
+        assert_eq!(cli.format, OutputFormat::Json);
+        let cli = Cli::try_parse_from(["arvore", "--format", "json-lines", "path", "x"]).unwrap();
+        assert_eq!(cli.format, OutputFormat::JsonLines);
+    }
+
     #[test]
     fn parse_missing_subcommand_errors() {
         let result = Cli::try_parse_from(["arvore"]);
@@ -1,22 +1,42 @@
-use anyhow::Result;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
 use colored::Colorize;
-use dialoguer::MultiSelect;
+use dialoguer::{Confirm, MultiSelect};
+use serde::Serialize;
 
-use crate::git;
+use crate::age;
+use crate::commands::remove::{self, RemoveOptions, Removed};
+use crate::config::{Config, DirtyPolicy};
+use crate::error::ArvoreError;
+use crate::git::{self, CommitInfo, MergeKind};
+use crate::hooks::HookContext;
+use crate::output::{self, OutputFormat};
+use crate::parallel;
+use crate::repos;
 
+#[derive(Serialize)]
 struct CleanCandidate {
     branch: String,
-    path: std::path::PathBuf,
+    path: PathBuf,
+    /// True however the branch was merged; `merged_by` tells how.
     merged: bool,
+    merged_by: Option<MergeKind>,
     remote_deleted: bool,
     dirty: bool,
+    locked: bool,
+    last_commit: Option<CommitInfo>,
 }
 
 impl CleanCandidate {
     fn reason(&self) -> String {
         let mut reasons = Vec::new();
-        if self.merged {
-            reasons.push("merged");
+        match self.merged_by {
+            Some(MergeKind::Merge) => reasons.push("merged"),
+            Some(MergeKind::Rebase) => reasons.push("rebase-merged"),
+            Some(MergeKind::Squash) => reasons.push("squash-merged"),
+            None => {}
         }
         if self.remote_deleted {
             reasons.push("remote deleted");
@@ -25,59 +45,413 @@ impl CleanCandidate {
     }
 }
 
-pub fn run(dry_run: bool) -> Result<()> {
+#[derive(Serialize)]
+struct Failed {
+    branch: String,
+    path: PathBuf,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct Kept {
+    branch: String,
+    path: PathBuf,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct CleanReport {
+    dry_run: bool,
+    /// Branch that merges were checked against.
+    base: String,
+    candidates: Vec<CleanCandidate>,
+    removed: Vec<Removed>,
+    /// Selected worktrees left in place because of `clean.dirty`.
+    kept: Vec<Kept>,
+    failed: Vec<Failed>,
+}
+
+pub struct CleanOptions<'a> {
+    /// Branch merges are checked against; detected when `None`.
+    pub base: Option<&'a str>,
+    pub dry_run: bool,
+    /// Remove dirty worktrees even if `clean.dirty` would keep them.
+    pub force: bool,
+    /// Consider locked worktrees too.
+    pub include_locked: bool,
+    /// Remove even if a pre_remove hook fails.
+    pub ignore_hook_failure: bool,
+    pub no_hooks: bool,
+    /// Remove every candidate instead of prompting.
+    pub yes: bool,
+    pub merged_only: bool,
+    pub remote_deleted_only: bool,
+    /// Minimum age of the last commit, in seconds.
+    pub older_than: Option<i64>,
+    pub skip_dirty: bool,
+}
+
+/// One repository's result in `clean --all-repos --format json`.
+#[derive(Serialize)]
+struct RepoReport {
+    repo: String,
+    main_worktree: PathBuf,
+    #[serde(flatten)]
+    report: CleanReport,
+}
+
+pub fn run(config: &Config, opts: &CleanOptions, format: OutputFormat) -> Result<()> {
     git::ensure_repo()?;
 
-    println!("{}", "Fetching and pruning remotes...".cyan());
+    let interactive = check_interactive(opts)?;
+    let report = sweep(config, opts, interactive, format)?;
+    output::emit(format, "clean", &report)
+}
+
+/// `clean --all-repos`: cleans every repository under `worktree_base`, one
+/// after the other.
+pub fn run_all_repos(
+    config_path: Option<&Path>,
+    config: &Config,
+    opts: &CleanOptions,
+    format: OutputFormat,
+) -> Result<()> {
+    let interactive = check_interactive(opts)?;
+    let (reports, failed) = repos::for_each(config_path, config, |repo, config| {
+        if format.is_text() {
+            println!(
+                "\n{} {}",
+                repo.name.bold(),
+                repo.main_worktree.display().to_string().dimmed()
+            );
+        }
+        Ok(RepoReport {
+            repo: repo.name.clone(),
+            main_worktree: repo.main_worktree.clone(),
+            report: sweep(config, opts, interactive, format)?,
+        })
+    })?;
+
+    output::emit_list(format, "clean", "repos", "repo", &reports)?;
+    if failed > 0 {
+        bail!("{failed} repositories could not be cleaned");
+    }
+    Ok(())
+}
+
+/// Whether worktrees can be picked on a terminal. Refuses up front when
+/// they would have to be, rather than after fetching, and before dialoguer
+/// fails on a missing terminal.
+fn check_interactive(opts: &CleanOptions) -> Result<bool> {
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    if !opts.yes && !opts.dry_run && !interactive {
+        bail!(ArvoreError::NotInteractive(
+            "choose worktrees to remove (pass --yes or --dry-run)".into()
+        ));
+    }
+    Ok(interactive)
+}
+
+/// Cleans the repository the process runs in.
+fn sweep(
+    config: &Config,
+    opts: &CleanOptions,
+    interactive: bool,
+    format: OutputFormat,
+) -> Result<CleanReport> {
+    if format.is_text() {
+        println!("{}", "Fetching and pruning remotes...".cyan());
+    }
     git::fetch_prune()?;
 
-    let main = git::main_branch()?;
-    let merged = git::merged_branches(&main)?;
+    let main = match opts.base {
+        Some(branch) => branch.to_string(),
+        None => git::main_branch(&config.remote, config.main_branch.as_deref())?,
+    };
+    let base_ref = git::local_or_remote(&config.remote, &main);
+    if format.is_text() {
+        println!("{} {}", "Comparing against".cyan(), base_ref.bold());
+    }
+    let merged = git::merged_branches(&base_ref)?;
+    let gone_branches = git::gone_branches()?;
     let worktrees = git::worktree_list()?;
 
-    let mut candidates: Vec<CleanCandidate> = Vec::new();
+    // The main worktree comes first and is never removable.
+    let eligible: Vec<(&PathBuf, &String, bool)> = worktrees
+        .iter()
+        .skip(1)
+        .filter(|wt| !wt.is_bare)
+        .filter(|wt| opts.include_locked || wt.locked.is_none())
+        .filter_map(|wt| Some((&wt.path, wt.branch.as_ref()?, wt.locked.is_some())))
+        .filter(|(_, branch, _)| **branch != main && !config.is_protected(branch))
+        .collect();
 
-    for wt in &worktrees {
-        if wt.is_bare {
+    // Squash and rebase merges need a few git calls per branch, so they are
+    // only looked for where a plain merge wasn't found.
+    let merge_kinds = parallel::map(&eligible, config.jobs, |(_, branch, _)| {
+        if merged.contains(*branch) {
+            Some(MergeKind::Merge)
+        } else {
+            git::detect_rebase_or_squash(&base_ref, branch).unwrap_or(None)
+        }
+    });
+
+    let mut candidates: Vec<CleanCandidate> = Vec::new();
+    for ((path, branch, locked), merged_by) in eligible.into_iter().zip(merge_kinds) {
+        let remote_deleted = gone_branches.contains(branch);
+        let wanted = if opts.merged_only {
+            merged_by.is_some()
+        } else if opts.remote_deleted_only {
+            remote_deleted
+        } else {
+            merged_by.is_some() || remote_deleted
+        };
+        if !wanted {
             continue;
         }
 
-        let branch = match &wt.branch {
-            Some(b) => b.clone(),
-            None => continue,
+        candidates.push(CleanCandidate {
+            branch: branch.clone(),
+            path: path.clone(),
+            merged: merged_by.is_some(),
+            merged_by,
+            remote_deleted,
+            dirty: false,
+            locked,
+            last_commit: None,
+        });
+    }
+
+    let states = parallel::map(&candidates, config.jobs, |c| {
+        if !c.path.exists() {
+            return (false, None);
+        }
+        (
+            git::is_dirty(&c.path).unwrap_or(false),
+            git::last_commit(&c.path),
+        )
+    });
+    for (c, (dirty, last_commit)) in candidates.iter_mut().zip(states) {
+        c.dirty = dirty;
+        c.last_commit = last_commit;
+    }
+
+    let cutoff = opts.older_than.map(|age| age::now() - age);
+    candidates.retain(|c| {
+        let old_enough = match cutoff {
+            Some(cutoff) => c
+                .last_commit
+                .as_ref()
+                .is_some_and(|l| l.timestamp <= cutoff),
+            None => true,
         };
+        old_enough && (!opts.skip_dirty || !c.dirty)
+    });
 
-        if branch == main {
-            continue;
+    let mut report = CleanReport {
+        dry_run: opts.dry_run,
+        base: main,
+        candidates: Vec::new(),
+        removed: Vec::new(),
+        kept: Vec::new(),
+        failed: Vec::new(),
+    };
+
+    if candidates.is_empty() {
+        if format.is_text() {
+            println!("{}", "No worktrees to clean up.".green());
         }
+        return Ok(report);
+    }
 
-        let is_merged = merged.contains(&branch);
-        let remote_deleted = !git::remote_branch_exists(&branch)?;
+    if format.is_text() {
+        print_candidates(&candidates);
+    }
 
-        if !is_merged && !remote_deleted {
-            continue;
+    if opts.dry_run {
+        if format.is_text() {
+            println!("{}", "Dry run - no worktrees removed.".cyan());
+        }
+        report.candidates = candidates;
+        return Ok(report);
+    }
+
+    let selections = if opts.yes {
+        (0..candidates.len()).collect()
+    } else {
+        select(&candidates)?
+    };
+
+    if selections.is_empty() {
+        if format.is_text() {
+            println!("{}", "Nothing selected.".yellow());
         }
+        report.candidates = candidates;
+        return Ok(report);
+    }
 
-        let dirty = if wt.path.exists() {
-            git::is_dirty(&wt.path).unwrap_or(false)
+    let repo = git::repo_info()?;
+
+    for idx in selections {
+        let c = &candidates[idx];
+        let snapshot = if c.dirty {
+            match dirty_action(config.clean_dirty, opts.force, c, interactive) {
+                Ok(DirtyAction::Snapshot) => match save_snapshot(c) {
+                    Ok(refname) => {
+                        if format.is_text() {
+                            println!(
+                                "{} Saved uncommitted changes of {} to {}",
+                                "✓".green().bold(),
+                                c.branch.cyan(),
+                                refname.bold()
+                            );
+                        }
+                        Some(refname)
+                    }
+                    Err(e) => {
+                        report_failure(&mut report.failed, c, &e);
+                        continue;
+                    }
+                },
+                Ok(DirtyAction::Discard) => None,
+                Ok(DirtyAction::Keep(reason)) => {
+                    if format.is_text() {
+                        println!("{} Kept {} ({reason})", "→".yellow(), c.branch.cyan());
+                    }
+                    report.kept.push(Kept {
+                        branch: c.branch.clone(),
+                        path: c.path.clone(),
+                        reason,
+                    });
+                    continue;
+                }
+                Err(e) => {
+                    report_failure(&mut report.failed, c, &e);
+                    continue;
+                }
+            }
         } else {
-            false
+            None
         };
 
-        candidates.push(CleanCandidate {
-            branch,
-            path: wt.path.clone(),
-            merged: is_merged,
-            remote_deleted,
-            dirty,
-        });
+        let ctx = HookContext {
+            repo: &repo.name,
+            branch: &c.branch,
+            worktree_path: &c.path,
+            main_worktree: &repo.main_worktree,
+            renamed_from: None,
+        };
+        let opts = RemoveOptions {
+            force: c.dirty,
+            ignore_hook_failure: opts.ignore_hook_failure,
+            no_hooks: opts.no_hooks,
+            unlock: c.locked,
+        };
+        match remove::remove_worktree(config, &ctx, &opts) {
+            Ok(trash) => {
+                if format.is_text() {
+                    println!("{} Removed {}", "✓".green().bold(), c.branch.cyan());
+                }
+                report.removed.push(Removed {
+                    branch: Some(c.branch.clone()),
+                    path: c.path.clone(),
+                    snapshot,
+                    trash,
+                });
+            }
+            Err(e) => report_failure(&mut report.failed, c, &e),
+        }
     }
 
-    if candidates.is_empty() {
-        println!("{}", "No worktrees to clean up.".green());
-        return Ok(());
+    git::worktree_prune()?;
+    if format.is_text() {
+        println!("\n{}", "Cleanup complete.".green().bold());
     }
 
+    report.candidates = candidates;
+    Ok(report)
+}
+
+enum DirtyAction {
+    Snapshot,
+    Discard,
+    Keep(String),
+}
+
+/// Decides what happens to the uncommitted changes of a selected worktree.
+/// `force` discards them where the policy would keep the worktree.
+fn dirty_action(
+    policy: DirtyPolicy,
+    force: bool,
+    c: &CleanCandidate,
+    interactive: bool,
+) -> Result<DirtyAction> {
+    Ok(match policy {
+        DirtyPolicy::Snapshot => DirtyAction::Snapshot,
+        _ if force => DirtyAction::Discard,
+        DirtyPolicy::Skip => DirtyAction::Keep("uncommitted changes".into()),
+        DirtyPolicy::Confirm if !interactive => {
+            DirtyAction::Keep("uncommitted changes, no terminal to confirm on".into())
+        }
+        DirtyPolicy::Confirm => {
+            let discard = Confirm::new()
+                .with_prompt(format!(
+                    "{} has uncommitted changes. Discard them?",
+                    c.branch
+                ))
+                .default(false)
+                .interact()?;
+            if discard {
+                DirtyAction::Discard
+            } else {
+                DirtyAction::Keep("uncommitted changes".into())
+            }
+        }
+    })
+}
+
+/// Saves the worktree's uncommitted changes under
+/// `refs/arvore/trash/<branch>/<timestamp>` and returns the ref.
+fn save_snapshot(c: &CleanCandidate) -> Result<String> {
+    let refname = format!("refs/arvore/trash/{}/{}", c.branch, age::now());
+    git::snapshot(
+        &c.path,
+        &refname,
+        &format!("Uncommitted changes of {}", c.branch),
+    )?;
+    Ok(refname)
+}
+
+fn report_failure(failed: &mut Vec<Failed>, c: &CleanCandidate, e: &anyhow::Error) {
+    eprintln!(
+        "{} Failed to remove {}: {:#}",
+        "✗".red().bold(),
+        c.branch.yellow(),
+        e
+    );
+    failed.push(Failed {
+        branch: c.branch.clone(),
+        path: c.path.clone(),
+        error: format!("{e:#}"),
+    });
+}
+
+fn select(candidates: &[CleanCandidate]) -> Result<Vec<usize>> {
+    let labels: Vec<String> = candidates
+        .iter()
+        .map(|c| {
+            let dirty_mark = if c.dirty { " ⚠ dirty" } else { "" };
+            format!("{} ({}){}", c.branch, c.reason(), dirty_mark)
+        })
+        .collect();
+
+    Ok(MultiSelect::new()
+        .with_prompt("Select worktrees to remove")
+        .items(&labels)
+        .interact()?)
+}
+
+fn print_candidates(candidates: &[CleanCandidate]) {
     println!(
         "\n{} candidate(s) for cleanup:\n",
         candidates.len().to_string().bold()
@@ -99,46 +473,4 @@ pub fn run(dry_run: bool) -> Result<()> {
         );
     }
     println!();
-
-    if dry_run {
-        println!("{}", "Dry run - no worktrees removed.".cyan());
-        return Ok(());
-    }
-
-    let labels: Vec<String> = candidates
-        .iter()
-        .map(|c| {
-            let dirty_mark = if c.dirty { " ⚠ dirty" } else { "" };
-            format!("{} ({}){}", c.branch, c.reason(), dirty_mark)
-        })
-        .collect();
-
-    let selections = MultiSelect::new()
-        .with_prompt("Select worktrees to remove")
-        .items(&labels)
-        .interact()?;
-
-    if selections.is_empty() {
-        println!("{}", "Nothing selected.".yellow());
-        return Ok(());
-    }
-
-    for idx in selections {
-        let c = &candidates[idx];
-        let force = c.dirty;
-        match git::worktree_remove(&c.path, force) {
-            Ok(()) => println!("{} Removed {}", "✓".green().bold(), c.branch.cyan()),
-            Err(e) => eprintln!(
-                "{} Failed to remove {}: {}",
-                "✗".red().bold(),
-                c.branch.yellow(),
-                e
-            ),
-        }
-    }
-
-    git::worktree_prune()?;
-    println!("\n{}", "Cleanup complete.".green().bold());
-
-    Ok(())
 }
@@ -1,44 +1,325 @@
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
 use colored::Colorize;
+use serde::Serialize;
 
 use crate::config::Config;
+use crate::error::ArvoreError;
+use crate::files::{self, CopySummary};
 use crate::git;
+use crate::hooks::{self, HookContext, HookKind};
+use crate::output::{self, OutputFormat};
+use crate::shell;
+
+#[derive(Serialize)]
+struct Created {
+    branch: String,
+    path: PathBuf,
+    new_branch: bool,
+    /// The pull/merge request checked out, if any.
+    pr: Option<u64>,
+    /// Remote the branch was fetched from, when it wasn't local.
+    remote: Option<String>,
+    files: CopySummary,
+}
 
-pub fn run(config: &Config, branch: &str, from: Option<&str>, open: bool) -> Result<()> {
+pub struct CreateOptions<'a> {
+    /// Branch to create or check out. With `pr`, names the local branch in
+    /// place of `review.branch_pattern`.
+    pub branch: Option<&'a str>,
+    /// Base ref for a new branch.
+    pub from: Option<&'a str>,
+    /// Pull/merge request to fetch and check out.
+    pub pr: Option<u64>,
+    /// Only look for the branch on this remote.
+    pub remote: Option<&'a str>,
+    pub open: bool,
+    pub no_hooks: bool,
+}
+
+pub fn run(config: &Config, opts: &CreateOptions, format: OutputFormat) -> Result<()> {
     git::ensure_repo()?;
 
-    let repo_name = git::repo_name()?;
-    let worktree_path = config.worktree_path(&repo_name, branch);
+    let branch = match (opts.pr, opts.branch) {
+        (_, Some(branch)) => branch.to_string(),
+        (Some(number), None) => config.review.branch(number),
+        (None, None) => bail!("a branch name or --pr is required"),
+    };
+    let branch = branch.as_str();
+
+    // Rather than silently checking out the local branch instead.
+    if let Some(remote) = opts.remote
+        && git::branch_exists_locally(branch)?
+    {
+        bail!(
+            "branch '{branch}' already exists locally; \
+             --remote {remote} only applies to branches checked out from a remote"
+        );
+    }
+
+    let repo = git::repo_info()?;
+    let worktree_path = config.worktree_path(&repo, branch)?;
+
+    let fetched_new_branch = match opts.pr {
+        Some(number) => Some(fetch_request(config, number, branch, format)?),
+        None => None,
+    };
 
     if let Some(parent) = worktree_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
-    match from {
-        Some(base_ref) => {
+    let (new_branch, remote) = match (fetched_new_branch, opts.from) {
+        (Some(new_branch), _) => {
+            if let Err(e) = git::worktree_add(&worktree_path, branch, false, None) {
+                if new_branch && let Err(e) = git::delete_branch(branch, true) {
+                    eprintln!(
+                        "{} Could not delete branch {}: {e}",
+                        "warning:".yellow().bold(),
+                        branch
+                    );
+                }
+                return Err(e);
+            }
+            (new_branch, Some(config.review.remote.clone()))
+        }
+        (None, Some(base_ref)) => {
             git::worktree_add(&worktree_path, branch, true, Some(base_ref))?;
+            (true, None)
         }
-        None => {
-            let exists_locally = git::branch_exists_locally(branch)?;
-            let exists_remotely = git::remote_branch_exists(branch)?;
-
-            if exists_locally || exists_remotely {
-                git::worktree_add(&worktree_path, branch, false, None)?;
-            } else {
+        (None, None) if git::branch_exists_locally(branch)? => {
+            git::worktree_add(&worktree_path, branch, false, None)?;
+            (false, None)
+        }
+        (None, None) => match find_remote(config, opts.remote, branch)? {
+            Some(remote) => {
+                let upstream = format!("{remote}/{branch}");
+                git::fetch_branch(&remote, branch)?;
+                git::worktree_add(&worktree_path, branch, true, Some(&upstream))?;
+                git::set_upstream(branch, &upstream)?;
+                if format.is_text() {
+                    println!(
+                        "{} Checked out {} tracking {}",
+                        "✓".green().bold(),
+                        branch.cyan(),
+                        upstream.cyan()
+                    );
+                }
+                (true, Some(remote))
+            }
+            None => {
                 git::worktree_add(&worktree_path, branch, true, None)?;
+                (true, None)
             }
+        },
+    };
+
+    if format.is_text() {
+        println!(
+            "{} Created worktree at {}",
+            "✓".green().bold(),
+            worktree_path.display().to_string().cyan()
+        );
+    }
+
+    let ctx = HookContext {
+        repo: &repo.name,
+        branch,
+        worktree_path: &worktree_path,
+        main_worktree: &repo.main_worktree,
+        renamed_from: None,
+    };
+    let files = match set_up(config, &ctx, opts.no_hooks, format) {
+        Ok(files) => files,
+        Err(e) => {
+            rollback(&worktree_path, branch, new_branch);
+            return Err(e);
         }
+    };
+
+    if opts.open {
+        crate::commands::open::open_path(
+            config,
+            &repo.name,
+            &worktree_path,
+            Some(branch),
+            None,
+            format,
+        )?;
     }
+    shell::request_cd(&worktree_path)?;
 
-    println!(
-        "{} Created worktree at {}",
-        "✓".green().bold(),
-        worktree_path.display().to_string().cyan()
-    );
+    output::emit(
+        format,
+        "create",
+        &Created {
+            branch: branch.to_string(),
+            path: worktree_path,
+            new_branch,
+            pr: opts.pr,
+            remote,
+            files,
+        },
+    )
+}
+
+/// The remote to check `branch` out from, if it exists on one. `requested`
+/// must have it; otherwise the configured remote is asked first, then the
+/// others, and finding it on several of those is an error.
+fn find_remote(config: &Config, requested: Option<&str>, branch: &str) -> Result<Option<String>> {
+    let remotes = git::remotes()?;
 
-    if open {
-        crate::commands::open::open_path(&worktree_path, true, true)?;
+    if let Some(remote) = requested {
+        if !remotes.iter().any(|r| r == remote) {
+            bail!(ArvoreError::GitError(format!("no remote named '{remote}'")));
+        }
+        if !git::remote_branch_exists(remote, branch)? {
+            bail!(ArvoreError::GitError(format!(
+                "branch '{branch}' not found on remote '{remote}'"
+            )));
+        }
+        return Ok(Some(remote.to_string()));
+    }
+
+    if remotes.contains(&config.remote) && git::remote_branch_exists(&config.remote, branch)? {
+        return Ok(Some(config.remote.clone()));
     }
 
-    Ok(())
+    let mut found = Vec::new();
+    for remote in remotes.into_iter().filter(|r| *r != config.remote) {
+        if git::remote_branch_exists(&remote, branch)? {
+            found.push(remote);
+        }
+    }
+    if found.len() > 1 {
+        bail!(
+            "branch '{branch}' exists on remotes {}; pick one with --remote",
+            found.join(", ")
+        );
+    }
+    Ok(found.pop())
+}
+
+/// Fetches the head of pull/merge request `number` into the local `branch`,
+/// creating it or resetting it to the request's head, which may have been
+/// force-pushed since. Returns whether the branch is new.
+fn fetch_request(config: &Config, number: u64, branch: &str, format: OutputFormat) -> Result<bool> {
+    // git won't move a branch checked out in a worktree, and the worktree
+    // the request would get is that one anyway.
+    if let Some(wt) = git::worktree_list()?
+        .into_iter()
+        .find(|wt| wt.branch.as_deref() == Some(branch))
+    {
+        bail!(
+            "#{number} is already checked out at {} (switch to it with: arvore switch {branch})",
+            wt.path.display()
+        );
+    }
+
+    let remote = &config.review.remote;
+    let src = config
+        .review
+        .remote_ref(number, git::remote_url(remote).as_deref());
+    let head = match git::fetch_commit(remote, &src) {
+        Ok(head) => head,
+        Err(e) => bail!("could not fetch #{number} ({src} from {remote}): {e}"),
+    };
+
+    // The head fetched last time, so that commits a force-push dropped from
+    // the request aren't mistaken for local work.
+    let fetched = format!("refs/arvore/review/{branch}");
+    let local = format!("refs/heads/{branch}");
+    let existed = git::branch_exists_locally(branch)?;
+    if existed {
+        let local_only = git::count_commits(&local, &[&head, &fetched])?;
+        if local_only > 0 {
+            bail!(
+                "branch '{branch}' has {local_only} commit(s) that #{number} doesn't; \
+                 push or move them, or delete the branch to check the request out again"
+            );
+        }
+    }
+    git::update_ref(&local, &head)?;
+    git::update_ref(&fetched, &head)?;
+    if format.is_text() {
+        println!(
+            "{} Fetched #{number} from {} into {}",
+            "✓".green().bold(),
+            remote,
+            branch.cyan()
+        );
+    }
+
+    Ok(!existed)
+}
+
+/// Copies untracked files over from the main worktree, then runs the
+/// `post_create` hooks.
+fn set_up(
+    config: &Config,
+    ctx: &HookContext,
+    no_hooks: bool,
+    format: OutputFormat,
+) -> Result<CopySummary> {
+    let summary = files::apply(config, ctx.main_worktree, ctx.worktree_path)?;
+    if format.is_text() && !summary.is_empty() {
+        let mut parts = Vec::new();
+        if !summary.copied.is_empty() {
+            parts.push(format!("copied {}", summary.copied.len()));
+        }
+        if !summary.symlinked.is_empty() {
+            parts.push(format!("symlinked {}", summary.symlinked.len()));
+        }
+        if !summary.skipped.is_empty() {
+            parts.push(format!("skipped {} existing", summary.skipped.len()));
+        }
+        println!(
+            "{} Untracked files: {}",
+            "✓".green().bold(),
+            parts.join(", ")
+        );
+        for rel in &summary.copied {
+            println!("    {} {}", "copy".dimmed(), rel.display());
+        }
+        for rel in &summary.symlinked {
+            println!("    {} {}", "link".dimmed(), rel.display());
+        }
+    }
+
+    if !no_hooks && !config.hooks.post_create.is_empty() {
+        hooks::run(
+            HookKind::PostCreate,
+            &config.hooks.post_create,
+            ctx,
+            ctx.worktree_path,
+        )?;
+    }
+
+    Ok(summary)
+}
+
+/// Undoes a creation whose setup failed, so a broken worktree is never left
+/// behind looking like a usable one.
+fn rollback(worktree_path: &Path, branch: &str, new_branch: bool) {
+    eprintln!(
+        "{} Setup failed, removing worktree {}",
+        "✗".red().bold(),
+        worktree_path.display().to_string().yellow()
+    );
+    if let Err(e) = git::worktree_remove(worktree_path, true) {
+        eprintln!(
+            "{} Could not remove {}: {e}",
+            "warning:".yellow().bold(),
+            worktree_path.display()
+        );
+        return;
+    }
+    if new_branch && let Err(e) = git::delete_branch(branch, true) {
+        eprintln!(
+            "{} Could not delete branch {}: {e}",
+            "warning:".yellow().bold(),
+            branch
+        );
+    }
 }
@@ -0,0 +1,397 @@
+use std::collections::HashSet;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use dialoguer::MultiSelect;
+use serde::Serialize;
+
+use crate::commands::remove;
+use crate::config::Config;
+use crate::error::ArvoreError;
+use crate::git::{self, RepoInfo, WorktreeInfo};
+use crate::output::{self, OutputFormat};
+use crate::parallel;
+use crate::trash;
+
+/// How deep below `worktree_base/<repo>` lost worktrees are looked for.
+const MAX_DEPTH: usize = 4;
+
+/// In the order fixes are applied: a moved worktree has to be re-linked
+/// before its old, prunable entry would be pruned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum IssueKind {
+    /// A worktree directory moved by hand; git still has it at the old path.
+    Moved,
+    /// A worktree whose `.git` file points where the repository used to be.
+    BrokenLink,
+    /// An entry git lists whose directory is gone.
+    Prunable,
+    /// A directory under the repository's worktrees git doesn't know about.
+    Orphaned,
+    /// A local branch merged into the main branch and not checked out in
+    /// any worktree.
+    UnusedBranch,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Fix {
+    Repair,
+    Prune,
+    Delete,
+    DeleteBranch,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Issue {
+    kind: IssueKind,
+    path: Option<PathBuf>,
+    branch: Option<String>,
+    detail: String,
+    fix: Fix,
+}
+
+impl Issue {
+    fn subject(&self) -> String {
+        match (&self.branch, &self.path) {
+            (Some(branch), Some(path)) => format!("{branch} ({})", path.display()),
+            (Some(branch), None) => branch.clone(),
+            (None, Some(path)) => path.display().to_string(),
+            (None, None) => String::new(),
+        }
+    }
+
+    fn label(&self) -> String {
+        let action = match self.fix {
+            Fix::Repair => "re-link",
+            Fix::Prune => "prune",
+            Fix::Delete => "delete",
+            Fix::DeleteBranch => "delete branch",
+        };
+        format!("{action} {} ({})", self.subject(), self.detail)
+    }
+}
+
+#[derive(Serialize)]
+struct Failed {
+    #[serde(flatten)]
+    issue: Issue,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct DoctorReport {
+    dry_run: bool,
+    issues: Vec<Issue>,
+    fixed: Vec<Issue>,
+    failed: Vec<Failed>,
+}
+
+pub fn run(config: &Config, dry_run: bool, yes: bool, format: OutputFormat) -> Result<()> {
+    git::ensure_repo()?;
+
+    let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    if !yes && !dry_run && !interactive {
+        bail!(ArvoreError::NotInteractive(
+            "choose problems to fix (pass --yes or --dry-run)".into()
+        ));
+    }
+
+    let repo = git::repo_info()?;
+    let issues = diagnose(config, &repo)?;
+    let mut report = DoctorReport {
+        dry_run,
+        issues: Vec::new(),
+        fixed: Vec::new(),
+        failed: Vec::new(),
+    };
+
+    if issues.is_empty() {
+        if format.is_text() {
+            println!("{}", "No problems found.".green());
+        }
+        return output::emit(format, "doctor", &report);
+    }
+
+    if format.is_text() {
+        print_issues(&issues);
+    }
+
+    if dry_run {
+        if format.is_text() {
+            println!("{}", "Dry run - nothing changed.".cyan());
+        }
+        report.issues = issues;
+        return output::emit(format, "doctor", &report);
+    }
+
+    let selections = if yes {
+        (0..issues.len()).collect()
+    } else {
+        select(&issues)?
+    };
+
+    if selections.is_empty() && format.is_text() {
+        println!("{}", "Nothing selected.".yellow());
+    }
+
+    for idx in selections {
+        let issue = &issues[idx];
+        match fix(config, &repo, issue) {
+            Ok(()) => {
+                if format.is_text() {
+                    println!("{} {}", "✓".green().bold(), issue.label());
+                }
+                report.fixed.push(issue.clone());
+            }
+            Err(e) => {
+                eprintln!("{} Failed to {}: {:#}", "✗".red().bold(), issue.label(), e);
+                report.failed.push(Failed {
+                    issue: issue.clone(),
+                    error: format!("{e:#}"),
+                });
+            }
+        }
+    }
+
+    report.issues = issues;
+    output::emit(format, "doctor", &report)
+}
+
+fn diagnose(config: &Config, repo: &RepoInfo) -> Result<Vec<Issue>> {
+    let worktrees = git::worktree_list()?;
+    let admin_root = git::common_dir()?.join("worktrees");
+    let mut issues = Vec::new();
+
+    // Worktree directories git lost track of. One whose `.git` file still
+    // leads to administrative files of this repository was moved and can be
+    // re-linked; one whose `.git` file leads nowhere is orphaned.
+    let known: Vec<PathBuf> = worktrees.iter().map(|wt| canonical(&wt.path)).collect();
+    let admin_root = canonical(&admin_root);
+    let mut moved_from = HashSet::new();
+    for dir in unknown_worktrees(&config.worktree_base.join(&repo.name), &known, 0)? {
+        // Relative when written by `worktree add --relative-paths`.
+        let Some(admin) = git::gitdir_file(&dir).map(|admin| canonical(&admin)) else {
+            continue;
+        };
+        if !admin.exists() {
+            issues.push(Issue {
+                kind: IssueKind::Orphaned,
+                path: Some(dir),
+                branch: None,
+                detail: "not a worktree git knows about".into(),
+                fix: Fix::Delete,
+            });
+            continue;
+        }
+        // A worktree of another repository.
+        if admin.parent() != Some(admin_root.as_path()) || !admin.is_dir() {
+            continue;
+        }
+
+        // `gitdir` in the administrative files names the old `.git` file,
+        // relative to them with `--relative-paths`.
+        let old_path = std::fs::read_to_string(admin.join("gitdir"))
+            .ok()
+            .and_then(|gitdir| {
+                let old = git::normalize(&admin.join(gitdir.trim()));
+                old.parent().map(Path::to_path_buf)
+            });
+        // Older git lists such a worktree by the relative path as written.
+        let old = old_path.as_ref().and_then(|old| {
+            worktrees.iter().find(|wt| {
+                &wt.path == old
+                    || (wt.path.is_relative() && &git::normalize(&admin.join(&wt.path)) == old)
+            })
+        });
+        let detail = match &old_path {
+            Some(old_path) => format!("moved here from {}", old_path.display()),
+            None => "moved here".into(),
+        };
+        issues.push(Issue {
+            kind: IssueKind::Moved,
+            path: Some(dir),
+            branch: old.and_then(|wt| wt.branch.clone()),
+            detail,
+            fix: Fix::Repair,
+        });
+        moved_from.extend(old.map(|wt| wt.path.clone()));
+        moved_from.extend(old_path);
+    }
+
+    // The main worktree comes first. Locked worktrees may be on a drive
+    // that isn't mounted, so they are left alone.
+    for wt in worktrees.iter().skip(1) {
+        if wt.is_bare || wt.locked.is_some() || moved_from.contains(&wt.path) {
+            continue;
+        }
+        if let Some(reason) = &wt.prunable {
+            issues.push(Issue {
+                kind: IssueKind::Prunable,
+                path: Some(wt.path.clone()),
+                branch: wt.branch.clone(),
+                detail: if reason.is_empty() {
+                    "directory is gone".into()
+                } else {
+                    reason.clone()
+                },
+                fix: Fix::Prune,
+            });
+        } else if let Some(admin) = git::gitdir_file(&wt.path)
+            && !admin.exists()
+        {
+            issues.push(Issue {
+                kind: IssueKind::BrokenLink,
+                path: Some(wt.path.clone()),
+                branch: wt.branch.clone(),
+                detail: format!(
+                    ".git points at {}, which is gone; was the repository moved?",
+                    admin.display()
+                ),
+                fix: Fix::Repair,
+            });
+        }
+    }
+
+    let unused = unused_branches(config, &worktrees, &issues)?;
+    issues.extend(unused);
+    issues.sort_by_key(|issue| issue.kind);
+    Ok(issues)
+}
+
+/// Directories under `root` with a `.git` file that aren't in `known`.
+/// Never looks inside a known worktree, so the contents of a main worktree
+/// cloned into `worktree_base` are left alone. Entries of `known` must be
+/// canonical.
+fn unknown_worktrees(root: &Path, known: &[PathBuf], depth: usize) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    if !root.is_dir() || known.iter().any(|k| canonical(root).starts_with(k)) {
+        return Ok(found);
+    }
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+        if !path.is_dir() || known.iter().any(|k| canonical(&path).starts_with(k)) {
+            continue;
+        }
+        let dot_git = path.join(".git");
+        if dot_git.is_file() {
+            found.push(path);
+        } else if !dot_git.exists() && depth < MAX_DEPTH {
+            // Templates like `{repo}/{branch}` nest worktrees in directories.
+            found.extend(unknown_worktrees(&path, known, depth + 1)?);
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Merged branches no worktree has checked out, counting those of prunable
+/// entries among `issues`: their branch is free once they are pruned.
+/// Merges are detected as `clean` does; unmerged branches are left alone.
+fn unused_branches(
+    config: &Config,
+    worktrees: &[WorktreeInfo],
+    issues: &[Issue],
+) -> Result<Vec<Issue>> {
+    let Ok(main) = git::main_branch(&config.remote, config.main_branch.as_deref()) else {
+        return Ok(Vec::new());
+    };
+    let base_ref = git::local_or_remote(&config.remote, &main);
+    let pruned: HashSet<&str> = issues
+        .iter()
+        .filter(|issue| issue.kind == IssueKind::Prunable)
+        .filter_map(|issue| issue.branch.as_deref())
+        .collect();
+    let checked_out: HashSet<&str> = worktrees
+        .iter()
+        .filter_map(|wt| wt.branch.as_deref())
+        .filter(|b| !pruned.contains(b))
+        .collect();
+
+    let free: Vec<String> = git::local_branches()?
+        .into_iter()
+        .filter(|b| !checked_out.contains(b.as_str()))
+        .filter(|b| *b != main && !config.is_protected(b))
+        .collect();
+    let merged = git::merged_branches(&base_ref)?;
+    let is_merged = parallel::map(&free, config.jobs, |b| {
+        merged.contains(b) || matches!(git::detect_rebase_or_squash(&base_ref, b), Ok(Some(_)))
+    });
+
+    Ok(free
+        .into_iter()
+        .zip(is_merged)
+        .filter(|(_, is_merged)| *is_merged)
+        .map(|(b, _)| Issue {
+            kind: IssueKind::UnusedBranch,
+            path: None,
+            detail: if pruned.contains(b.as_str()) {
+                format!("merged into {main}, only checked out in a prunable worktree")
+            } else {
+                format!("merged into {main}, not checked out in any worktree")
+            },
+            branch: Some(b),
+            fix: Fix::DeleteBranch,
+        })
+        .collect())
+}
+
+fn fix(config: &Config, repo: &RepoInfo, issue: &Issue) -> Result<()> {
+    match (issue.fix, &issue.path, &issue.branch) {
+        (Fix::Repair, Some(path), _) => git::worktree_repair(path),
+        // Removing a worktree whose directory is gone drops just its entry.
+        (Fix::Prune, Some(path), _) => git::worktree_remove(path, true),
+        (Fix::Delete, Some(path), _) => {
+            if config.trash {
+                trash::move_in(config, &repo.name, &repo.main_worktree, None, path)?;
+            } else {
+                std::fs::remove_dir_all(path)?;
+            }
+            remove::cleanup_empty_parents(path);
+            Ok(())
+        }
+        // Only merged branches; git explains how to delete the others.
+        // Its merge into the main branch was checked already; `-d` would
+        // check against HEAD and miss squash and rebase merges.
+        (Fix::DeleteBranch, _, Some(branch)) => git::delete_branch(branch, true),
+        _ => unreachable!("every issue carries what its fix needs"),
+    }
+}
+
+fn select(issues: &[Issue]) -> Result<Vec<usize>> {
+    let labels: Vec<String> = issues.iter().map(Issue::label).collect();
+
+    Ok(MultiSelect::new()
+        .with_prompt("Select problems to fix")
+        .items(&labels)
+        .interact()?)
+}
+
+fn print_issues(issues: &[Issue]) {
+    println!("\n{} problem(s) found:\n", issues.len().to_string().bold());
+
+    for (i, issue) in issues.iter().enumerate() {
+        let kind = match issue.kind {
+            IssueKind::Moved => "moved",
+            IssueKind::BrokenLink => "broken link",
+            IssueKind::Prunable => "prunable",
+            IssueKind::Orphaned => "orphaned",
+            IssueKind::UnusedBranch => "unused branch",
+        };
+        println!(
+            "  {}. {} {}\n     {}",
+            i + 1,
+            format!("[{kind}]").yellow().bold(),
+            issue.subject(),
+            issue.detail.dimmed()
+        );
+    }
+    println!();
+}
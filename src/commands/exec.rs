@@ -0,0 +1,271 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::git::{self, WorktreeInfo};
+use crate::hooks::HookContext;
+use crate::output::{self, OutputFormat};
+use crate::parallel;
+
+pub struct ExecOptions<'a> {
+    pub branches: Option<&'a str>,
+    /// `Some(true)` for `--dirty`, `Some(false)` for `--clean`.
+    pub dirty: Option<bool>,
+    pub parallel: usize,
+    pub command: &'a [String],
+}
+
+#[derive(Serialize)]
+struct ExecResult {
+    branch: Option<String>,
+    path: PathBuf,
+    /// `None` when the command couldn't start or was killed by a signal.
+    exit_code: Option<i32>,
+    success: bool,
+    error: Option<String>,
+    /// Captured output; empty in text mode when it went to the terminal.
+    stdout: String,
+    stderr: String,
+}
+
+impl ExecResult {
+    fn name(&self) -> &str {
+        self.branch.as_deref().unwrap_or("(detached)")
+    }
+}
+
+#[derive(Serialize)]
+struct ExecReport {
+    argv: Vec<String>,
+    results: Vec<ExecResult>,
+}
+
+/// Runs a command in each matching worktree. One at a time, its output goes
+/// straight to the terminal; in parallel, each worktree's output is printed
+/// in one block once its command is done.
+pub fn run(config: &Config, opts: &ExecOptions, format: OutputFormat) -> Result<()> {
+    git::ensure_repo()?;
+
+    let pattern = opts
+        .branches
+        .map(glob::Pattern::new)
+        .transpose()
+        .map_err(|e| anyhow!("invalid --branches pattern: {e}"))?;
+    let worktrees = git::worktree_list()?;
+    let mut selected = select(&worktrees, pattern.as_ref());
+    if let Some(want_dirty) = opts.dirty {
+        let dirty_flags = parallel::map(&selected, config.jobs, |wt| {
+            git::is_dirty(&wt.path).unwrap_or(false)
+        });
+        let mut flags = dirty_flags.into_iter();
+        selected.retain(|_| flags.next() == Some(want_dirty));
+    }
+
+    let mut report = ExecReport {
+        argv: opts.command.to_vec(),
+        results: Vec::new(),
+    };
+    if selected.is_empty() {
+        if format.is_text() {
+            println!("{}", "No worktrees match.".yellow());
+        }
+        return output::emit(format, "exec", &report);
+    }
+
+    let repo = git::repo_info()?;
+    let stream = format.is_text() && opts.parallel <= 1;
+    report.results = parallel::map(&selected, opts.parallel, |wt| {
+        let ctx = HookContext {
+            repo: &repo.name,
+            branch: wt.branch.as_deref().unwrap_or(""),
+            worktree_path: &wt.path,
+            main_worktree: &repo.main_worktree,
+            renamed_from: None,
+        };
+        if stream {
+            print_header(wt);
+        }
+        let result = run_one(wt, opts.command, &ctx, stream);
+        if format.is_text() && !stream {
+            print_captured(wt, &result);
+        }
+        result
+    });
+
+    let failed = report.results.iter().filter(|r| !r.success).count();
+    if format.is_text() {
+        print_summary(&report.results);
+    }
+    output::emit(format, "exec", &report)?;
+    if failed > 0 {
+        bail!(
+            "command failed in {failed} of {} worktrees",
+            report.results.len()
+        );
+    }
+    Ok(())
+}
+
+/// Worktrees the command can run in, narrowed to branches matching
+/// `pattern`, either literally or as a glob.
+fn select<'a>(
+    worktrees: &'a [WorktreeInfo],
+    pattern: Option<&glob::Pattern>,
+) -> Vec<&'a WorktreeInfo> {
+    worktrees
+        .iter()
+        .filter(|wt| !wt.is_bare && wt.path.is_dir())
+        .filter(|wt| match (pattern, &wt.branch) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(p), Some(branch)) => p.as_str() == branch || p.matches(branch),
+        })
+        .collect()
+}
+
+fn run_one(wt: &WorktreeInfo, argv: &[String], ctx: &HookContext, stream: bool) -> ExecResult {
+    let (stdout, stderr) = if stream {
+        (Stdio::inherit(), Stdio::inherit())
+    } else {
+        (Stdio::piped(), Stdio::piped())
+    };
+    let output = Command::new(&argv[0])
+        .args(&argv[1..])
+        .current_dir(&wt.path)
+        .envs(ctx.env())
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(stderr)
+        .spawn()
+        .and_then(|child| child.wait_with_output());
+
+    let mut result = ExecResult {
+        branch: wt.branch.clone(),
+        path: wt.path.clone(),
+        exit_code: None,
+        success: false,
+        error: None,
+        stdout: String::new(),
+        stderr: String::new(),
+    };
+    match output {
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        }) => {
+            result.exit_code = status.code();
+            result.success = status.success();
+            if status.code().is_none() {
+                result.error = Some(status.to_string());
+            }
+            result.stdout = String::from_utf8_lossy(&stdout).into_owned();
+            result.stderr = String::from_utf8_lossy(&stderr).into_owned();
+        }
+        Err(e) => result.error = Some(format!("could not run {}: {e}", argv[0])),
+    }
+    result
+}
+
+fn print_header(wt: &WorktreeInfo) {
+    println!(
+        "{} {} {}",
+        "→".cyan().bold(),
+        wt.branch.as_deref().unwrap_or("(detached)").bold(),
+        wt.path.display().to_string().dimmed()
+    );
+}
+
+/// Prints a worktree's header and output in one go, so blocks from
+/// commands running side by side don't interleave.
+fn print_captured(wt: &WorktreeInfo, result: &ExecResult) {
+    let mut out = std::io::stdout().lock();
+    let mut err = std::io::stderr().lock();
+    print_header(wt);
+    let _ = out.write_all(result.stdout.as_bytes());
+    let _ = err.write_all(result.stderr.as_bytes());
+    if let Some(error) = &result.error {
+        let _ = writeln!(err, "{} {error}", "error:".red().bold());
+    }
+    let _ = out.flush();
+}
+
+fn print_summary(results: &[ExecResult]) {
+    let width = results
+        .iter()
+        .map(|r| r.name().chars().count())
+        .max()
+        .unwrap_or(0);
+    println!();
+    for result in results {
+        let code = format!(
+            "{:>3}",
+            result.exit_code.map_or("-".to_string(), |c| c.to_string())
+        );
+        let (mark, code) = if result.success {
+            ("✓".green().bold(), code.green())
+        } else {
+            ("✗".red().bold(), code.red())
+        };
+        let detail = result
+            .error
+            .clone()
+            .unwrap_or_else(|| result.path.display().to_string());
+        println!(
+            "  {mark} {code}  {}  {}",
+            format!("{:<width$}", result.name()).bold(),
+            detail.dimmed()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worktree(path: &std::path::Path, branch: Option<&str>) -> WorktreeInfo {
+        WorktreeInfo {
+            path: path.to_path_buf(),
+            branch: branch.map(str::to_string),
+            head: "abc123".into(),
+            is_bare: false,
+            locked: None,
+            prunable: None,
+        }
+    }
+
+    #[test]
+    fn select_filters_by_branch_glob_and_skips_missing() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let worktrees = vec![
+            worktree(dir, Some("main")),
+            worktree(dir, Some("feature/auth")),
+            worktree(dir, Some("feature/billing")),
+            worktree(dir, None),
+            worktree(&dir.join("gone"), Some("feature/gone")),
+        ];
+        let branches = |sel: Vec<&WorktreeInfo>| -> Vec<Option<String>> {
+            sel.into_iter().map(|wt| wt.branch.clone()).collect()
+        };
+
+        assert_eq!(select(&worktrees, None).len(), 4);
+
+        let pattern = glob::Pattern::new("feature/*").unwrap();
+        assert_eq!(
+            branches(select(&worktrees, Some(&pattern))),
+            [Some("feature/auth".into()), Some("feature/billing".into())]
+        );
+
+        let pattern = glob::Pattern::new("main").unwrap();
+        assert_eq!(
+            branches(select(&worktrees, Some(&pattern))),
+            [Some("main".into())]
+        );
+    }
+}
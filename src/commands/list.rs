@@ -1,30 +1,106 @@
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
 use colored::Colorize;
+use serde::Serialize;
+
+use crate::age;
+use crate::config::{Column, Config};
+use crate::git::{self, CommitInfo, WorktreeInfo};
+use crate::output::{self, OutputFormat};
+use crate::parallel;
+use crate::repos;
 
-use crate::git;
+/// A worktree as reported by `ls --format json`.
+#[derive(Debug, Serialize)]
+pub struct WorktreeStatus {
+    pub path: PathBuf,
+    pub branch: Option<String>,
+    pub head: String,
+    pub bare: bool,
+    pub main: bool,
+    pub locked: bool,
+    pub lock_reason: Option<String>,
+    /// Its directory is gone; `git worktree prune` would forget it.
+    pub prunable: bool,
+    pub prunable_reason: Option<String>,
+    pub dirty: bool,
+    pub upstream: Option<String>,
+    pub ahead: Option<usize>,
+    pub behind: Option<usize>,
+    /// Commits ahead of the main branch.
+    pub main_ahead: Option<usize>,
+    /// Commits behind the main branch.
+    pub main_behind: Option<usize>,
+    pub last_commit: Option<CommitInfo>,
+}
 
-pub fn run(porcelain: bool) -> Result<()> {
+impl WorktreeStatus {
+    /// Gathers the worktree's state. Comparing against the main branch is
+    /// skipped when `main_branch` is `None`.
+    pub fn collect(wt: &WorktreeInfo, main: bool, main_branch: Option<&str>) -> Self {
+        let present = !wt.is_bare && wt.path.exists();
+        let dirty = present && git::is_dirty(&wt.path).unwrap_or(false);
+        let upstream = if present {
+            git::upstream(&wt.path)
+        } else {
+            None
+        };
+        let compare = |other: &str| match git::ahead_behind(&wt.path, other) {
+            Ok((a, b)) => (Some(a), Some(b)),
+            Err(_) => (None, None),
+        };
+        let (ahead, behind) = upstream.as_deref().map_or((None, None), compare);
+        let (main_ahead, main_behind) = match main_branch {
+            Some(m) if present => compare(m),
+            _ => (None, None),
+        };
+        let last_commit = if present {
+            git::last_commit(&wt.path)
+        } else {
+            None
+        };
+
+        WorktreeStatus {
+            path: wt.path.clone(),
+            branch: wt.branch.clone(),
+            head: wt.head.clone(),
+            bare: wt.is_bare,
+            main,
+            locked: wt.locked.is_some(),
+            lock_reason: wt.locked.clone().filter(|r| !r.is_empty()),
+            prunable: wt.prunable.is_some(),
+            prunable_reason: wt.prunable.clone().filter(|r| !r.is_empty()),
+            dirty,
+            upstream,
+            ahead,
+            behind,
+            main_ahead,
+            main_behind,
+            last_commit,
+        }
+    }
+}
+
+pub fn run(
+    config: &Config,
+    porcelain: bool,
+    columns: Option<&[Column]>,
+    format: OutputFormat,
+) -> Result<()> {
     git::ensure_repo()?;
 
     let worktrees = git::worktree_list()?;
+    let columns = columns.unwrap_or(&config.ls_columns);
+
+    if !format.is_text() {
+        let main_branch = main_ref(config);
+        let statuses = collect_all(&worktrees, main_branch.as_deref(), config.jobs);
+        return output::emit_list(format, "ls", "worktrees", "worktree", &statuses);
+    }
 
     if porcelain {
-        for wt in &worktrees {
-            let branch_name = wt.branch.as_deref().unwrap_or("(detached)");
-            let dirty = if !wt.is_bare && wt.path.exists() {
-                git::is_dirty(&wt.path).unwrap_or(false)
-            } else {
-                false
-            };
-            let short_head = &wt.head[..wt.head.len().min(8)];
-            println!(
-                "{}\t{}\t{}\t{}",
-                branch_name,
-                wt.path.display(),
-                if dirty { "dirty" } else { "clean" },
-                short_head
-            );
-        }
+        print_porcelain(&worktrees, None, config.jobs);
         return Ok(());
     }
 
@@ -33,35 +109,248 @@ pub fn run(porcelain: bool) -> Result<()> {
         return Ok(());
     }
 
-    for wt in &worktrees {
-        let branch_name = wt.branch.as_deref().unwrap_or("(detached)");
-        let dirty = if !wt.is_bare && wt.path.exists() {
-            git::is_dirty(&wt.path).unwrap_or(false)
-        } else {
-            false
-        };
-        let short_head = &wt.head[..wt.head.len().min(8)];
+    let main_branch = if columns.contains(&Column::Main) {
+        main_ref(config)
+    } else {
+        None
+    };
+    let statuses = collect_all(&worktrees, main_branch.as_deref(), config.jobs);
 
-        let branch_display = if dirty {
-            branch_name.yellow().bold().to_string()
-        } else {
-            branch_name.green().bold().to_string()
-        };
+    print_table(&statuses, columns, age::now());
 
-        let dirty_indicator = if dirty {
-            " [modified]".red().to_string()
-        } else {
-            String::new()
-        };
+    Ok(())
+}
+
+/// Worktrees of one repository in `ls --all-repos --format json`.
+#[derive(Serialize)]
+struct RepoWorktrees {
+    repo: String,
+    main_worktree: PathBuf,
+    worktrees: Vec<WorktreeStatus>,
+}
+
+/// `ls --all-repos`: the worktrees of every repository under
+/// `worktree_base`, grouped by repository.
+pub fn run_all_repos(
+    config_path: Option<&Path>,
+    config: &Config,
+    porcelain: bool,
+    columns: Option<&[Column]>,
+    format: OutputFormat,
+) -> Result<()> {
+    let (groups, failed) = repos::for_each(config_path, config, |repo, config| {
+        let worktrees = git::worktree_list()?;
+
+        if format.is_text() && porcelain {
+            print_porcelain(&worktrees, Some(&repo.name), config.jobs);
+            return Ok(None);
+        }
+        if format.is_text() {
+            let columns = columns.unwrap_or(&config.ls_columns);
+            let main_branch = if columns.contains(&Column::Main) {
+                main_ref(config)
+            } else {
+                None
+            };
+            let statuses = collect_all(&worktrees, main_branch.as_deref(), config.jobs);
+            println!(
+                "{} {}",
+                repo.name.bold(),
+                repo.main_worktree.display().to_string().dimmed()
+            );
+            print_table(&statuses, columns, age::now());
+            println!();
+            return Ok(None);
+        }
+
+        let main_branch = main_ref(config);
+        Ok(Some(RepoWorktrees {
+            repo: repo.name.clone(),
+            main_worktree: repo.main_worktree.clone(),
+            worktrees: collect_all(&worktrees, main_branch.as_deref(), config.jobs),
+        }))
+    })?;
 
+    let groups: Vec<RepoWorktrees> = groups.into_iter().flatten().collect();
+    if format.is_text() && !porcelain && groups.is_empty() && failed == 0 {
         println!(
-            "  {} {} {}{}",
-            short_head.dimmed(),
-            branch_display,
-            wt.path.display().to_string().dimmed(),
-            dirty_indicator
+            "{}",
+            format!(
+                "No worktrees found under {}.",
+                config.worktree_base.display()
+            )
+            .yellow()
         );
     }
-
+    output::emit_list(format, "ls", "repos", "repo", &groups)?;
+    if failed > 0 {
+        bail!("{failed} repositories could not be listed");
+    }
     Ok(())
 }
+
+/// One `branch<TAB>path<TAB>dirty|clean<TAB>head` line per worktree, with
+/// the repository name as an extra first field when given.
+fn print_porcelain(worktrees: &[WorktreeInfo], repo: Option<&str>, jobs: usize) {
+    let dirty_flags = parallel::map(worktrees, jobs, |wt| {
+        !wt.is_bare && wt.path.exists() && git::is_dirty(&wt.path).unwrap_or(false)
+    });
+    for (wt, dirty) in worktrees.iter().zip(dirty_flags) {
+        let branch_name = wt.branch.as_deref().unwrap_or("(detached)");
+        let short_head = &wt.head[..wt.head.len().min(8)];
+        if let Some(repo) = repo {
+            print!("{repo}\t");
+        }
+        println!(
+            "{}\t{}\t{}\t{}",
+            branch_name,
+            wt.path.display(),
+            if dirty { "dirty" } else { "clean" },
+            short_head
+        );
+    }
+}
+
+/// Collects every worktree's status concurrently; the first worktree is the
+/// main one.
+pub fn collect_all(
+    worktrees: &[WorktreeInfo],
+    main_branch: Option<&str>,
+    jobs: usize,
+) -> Vec<WorktreeStatus> {
+    let main_path = worktrees.first().map(|wt| wt.path.clone());
+    parallel::map(worktrees, jobs, |wt| {
+        WorktreeStatus::collect(wt, Some(&wt.path) == main_path.as_ref(), main_branch)
+    })
+}
+
+/// The main branch as a ref to compare against, if it can be found.
+fn main_ref(config: &Config) -> Option<String> {
+    let branch = git::main_branch(&config.remote, config.main_branch.as_deref()).ok()?;
+    Some(git::local_or_remote(&config.remote, &branch))
+}
+
+fn print_table(statuses: &[WorktreeStatus], columns: &[Column], now: i64) {
+    let header: Vec<String> = columns.iter().map(|&c| heading(c).to_string()).collect();
+    let rows: Vec<Vec<String>> = statuses
+        .iter()
+        .map(|s| columns.iter().map(|&c| cell(c, s, now)).collect())
+        .collect();
+
+    let widths: Vec<usize> = (0..columns.len())
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(&rows)
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut line = String::from(" ");
+    for (i, text) in header.iter().enumerate() {
+        line.push(' ');
+        line.push_str(&format!("{text:<width$}", width = widths[i]));
+    }
+    println!("{}", line.trim_end().dimmed());
+
+    for (status, row) in statuses.iter().zip(&rows) {
+        let mut line = String::from(" ");
+        for (i, (&column, text)) in columns.iter().zip(row).enumerate() {
+            // Pad before painting so escape codes don't skew the alignment.
+            let padded = if i + 1 == columns.len() {
+                text.clone()
+            } else {
+                format!("{text:<width$}", width = widths[i])
+            };
+            line.push(' ');
+            line.push_str(&paint(column, padded, status));
+        }
+        println!("{}", line.trim_end());
+    }
+}
+
+fn heading(column: Column) -> &'static str {
+    match column {
+        Column::Head => "HEAD",
+        Column::Branch => "BRANCH",
+        Column::Status => "",
+        Column::Upstream => "UPSTREAM",
+        Column::Sync => "SYNC",
+        Column::Main => "MAIN",
+        Column::Age => "AGE",
+        Column::Subject => "SUBJECT",
+        Column::Path => "PATH",
+    }
+}
+
+fn cell(column: Column, s: &WorktreeStatus, now: i64) -> String {
+    match column {
+        Column::Head => s.head[..s.head.len().min(8)].to_string(),
+        Column::Branch => s.branch.as_deref().unwrap_or("(detached)").to_string(),
+        Column::Status => status_flags(s),
+        Column::Upstream => s.upstream.as_deref().unwrap_or("-").to_string(),
+        Column::Sync => format_ahead_behind(s.ahead, s.behind),
+        Column::Main => format_ahead_behind(s.main_ahead, s.main_behind),
+        Column::Age => s
+            .last_commit
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |c| age::format(now - c.timestamp)),
+        Column::Subject => s
+            .last_commit
+            .as_ref()
+            .map(|c| c.subject.clone())
+            .unwrap_or_default(),
+        Column::Path => s.path.display().to_string(),
+    }
+}
+
+fn paint(column: Column, text: String, s: &WorktreeStatus) -> String {
+    match column {
+        Column::Head | Column::Path | Column::Age | Column::Subject => text.dimmed().to_string(),
+        Column::Branch if s.dirty => text.yellow().bold().to_string(),
+        Column::Branch => text.green().bold().to_string(),
+        Column::Status => text.red().to_string(),
+        Column::Upstream => text.cyan().to_string(),
+        Column::Sync | Column::Main => text,
+    }
+}
+
+/// `[modified]`, `[locked]` and `[prunable]`, whichever apply.
+fn status_flags(s: &WorktreeStatus) -> String {
+    [
+        (s.dirty, "[modified]"),
+        (s.locked, "[locked]"),
+        (s.prunable, "[prunable]"),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, flag)| *flag)
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// `↑2 ↓1`, `✓` when in sync, `-` when there is nothing to compare with.
+fn format_ahead_behind(ahead: Option<usize>, behind: Option<usize>) -> String {
+    match (ahead, behind) {
+        (Some(0), Some(0)) => "✓".to_string(),
+        (Some(a), Some(0)) => format!("↑{a}"),
+        (Some(0), Some(b)) => format!("↓{b}"),
+        (Some(a), Some(b)) => format!("↑{a} ↓{b}"),
+        _ => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ahead_behind_formatting() {
+        assert_eq!(format_ahead_behind(Some(0), Some(0)), "✓");
+        assert_eq!(format_ahead_behind(Some(2), Some(0)), "↑2");
+        assert_eq!(format_ahead_behind(Some(0), Some(3)), "↓3");
+        assert_eq!(format_ahead_behind(Some(2), Some(3)), "↑2 ↓3");
+        assert_eq!(format_ahead_behind(None, None), "-");
+    }
+}
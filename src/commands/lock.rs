@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::error::ArvoreError;
+use crate::git;
+use crate::output::{self, OutputFormat};
+
+#[derive(Serialize)]
+struct LockState {
+    branch: Option<String>,
+    path: PathBuf,
+    locked: bool,
+    lock_reason: Option<String>,
+}
+
+/// Locks a worktree so `rm`, `clean` and `git worktree prune` leave it
+/// alone, e.g. while it lives on a drive that isn't always mounted.
+pub fn lock(target: &str, reason: Option<&str>, format: OutputFormat) -> Result<()> {
+    git::ensure_repo()?;
+
+    let wt = git::resolve_worktree(target)?;
+    if wt.path == git::main_worktree()? {
+        bail!(ArvoreError::MainWorktree(target.to_string()));
+    }
+    if wt.locked.is_some() {
+        bail!("worktree '{target}' is already locked");
+    }
+    git::worktree_lock(&wt.path, reason)?;
+
+    if format.is_text() {
+        println!("{} Locked {}", "✓".green().bold(), target.cyan());
+    }
+    output::emit(
+        format,
+        "lock",
+        &LockState {
+            branch: wt.branch,
+            path: wt.path,
+            locked: true,
+            lock_reason: reason.map(str::to_string),
+        },
+    )
+}
+
+pub fn unlock(target: &str, format: OutputFormat) -> Result<()> {
+    git::ensure_repo()?;
+
+    let wt = git::resolve_worktree(target)?;
+    if wt.locked.is_none() {
+        bail!("worktree '{target}' is not locked");
+    }
+    git::worktree_unlock(&wt.path)?;
+
+    if format.is_text() {
+        println!("{} Unlocked {}", "✓".green().bold(), target.cyan());
+    }
+    output::emit(
+        format,
+        "unlock",
+        &LockState {
+            branch: wt.branch,
+            path: wt.path,
+            locked: false,
+            lock_reason: None,
+        },
+    )
+}
@@ -1,7 +1,15 @@
 pub mod clean;
 pub mod completions;
 pub mod create;
+pub mod doctor;
+pub mod exec;
 pub mod list;
+pub mod lock;
 pub mod open;
 pub mod path;
 pub mod remove;
+pub mod rename;
+pub mod restore;
+pub mod shell_init;
+pub mod switch;
+pub mod trash;
@@ -1,47 +1,160 @@
-use std::path::Path;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 use anyhow::{bail, Result};
 use colored::Colorize;
+use serde::Serialize;
 
-use crate::config::Config;
+use crate::config::{self, Config, Opener};
 use crate::error::ArvoreError;
 use crate::git;
+use crate::output::{self, OutputFormat};
+use crate::session;
 
-pub fn run(config: &Config, branch: &str, cursor: bool, warp: bool, all: bool) -> Result<()> {
+#[derive(Serialize)]
+struct Opened {
+    path: PathBuf,
+    opened: Vec<String>,
+}
+
+pub fn run(
+    config: &Config,
+    branch: &str,
+    with: Option<&[String]>,
+    format: OutputFormat,
+) -> Result<()> {
     git::ensure_repo()?;
 
-    let repo_name = git::repo_name()?;
-    let worktree_path = config.worktree_path(&repo_name, branch);
+    let wt = git::resolve_worktree(branch)?;
 
-    if !worktree_path.exists() {
+    if !wt.path.exists() {
         bail!(ArvoreError::WorktreeNotFound(branch.to_string()));
     }
 
-    let open_warp = all || warp || !cursor;
-    let open_cursor = all || cursor || !warp;
+    let repo = git::repo_info()?;
+    let opened = open_path(
+        config,
+        &repo.name,
+        &wt.path,
+        wt.branch.as_deref(),
+        with,
+        format,
+    )?;
 
-    open_path(&worktree_path, open_warp, open_cursor)?;
+    output::emit(
+        format,
+        "open",
+        &Opened {
+            path: wt.path,
+            opened,
+        },
+    )
+}
 
-    Ok(())
+/// Opens `path` with the openers named in `with`, or the configured
+/// `open_with` list. Defaults that aren't available on this platform are
+/// skipped; naming one explicitly is an error. Returns the openers used.
+pub fn open_path(
+    config: &Config,
+    repo: &str,
+    path: &Path,
+    branch: Option<&str>,
+    with: Option<&[String]>,
+    format: OutputFormat,
+) -> Result<Vec<String>> {
+    let explicit = with.is_some();
+    let names = with.unwrap_or(&config.open_with);
+    let mut opened = Vec::new();
+
+    for name in names {
+        let opener = config.openers.get(name).ok_or_else(|| {
+            ArvoreError::ConfigError(format!(
+                "unknown opener '{name}' (available: {})",
+                config
+                    .openers
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?;
+        if !opener.is_available() {
+            if explicit {
+                bail!(ArvoreError::OpenFailed(
+                    name.clone(),
+                    format!("not available on {}", std::env::consts::OS)
+                ));
+            }
+            continue;
+        }
+
+        launch(config, name, opener, repo, path, branch, format)?;
+        if format.is_text() {
+            println!("{} Opened in {}", "✓".green().bold(), name.cyan());
+        }
+        opened.push(name.clone());
+    }
+
+    if opened.is_empty() {
+        bail!(ArvoreError::ConfigError(format!(
+            "no opener in open_with is available on {}; add one or pass --with",
+            std::env::consts::OS
+        )));
+    }
+
+    Ok(opened)
 }
 
-pub fn open_path(path: &Path, warp: bool, cursor: bool) -> Result<()> {
-    if warp {
-        Command::new("open")
-            .args(["-a", "Warp"])
-            .arg(path)
-            .spawn()
-            .map_err(|e| ArvoreError::GitError(format!("failed to open Warp: {e}")))?;
-        println!("{} Opened in {}", "✓".green().bold(), "Warp".cyan());
+fn launch(
+    config: &Config,
+    name: &str,
+    opener: &Opener,
+    repo: &str,
+    path: &Path,
+    branch: Option<&str>,
+    format: OutputFormat,
+) -> Result<()> {
+    if let Some(mux) = opener.session {
+        // Detached HEADs have no branch; the directory name stands in.
+        let branch = branch.map_or_else(
+            || path.file_name().unwrap_or_default().to_string_lossy(),
+            Into::into,
+        );
+        return session::open(config, mux, repo, &branch, path, format);
     }
 
-    if cursor {
-        Command::new("cursor")
-            .arg(path)
+    let args = opener
+        .args
+        .iter()
+        .map(|arg| {
+            config::render_template(arg, |placeholder| {
+                Ok(match placeholder {
+                    "path" => path.to_string_lossy().into_owned(),
+                    "branch" => branch.unwrap_or_default().to_string(),
+                    "repo" => repo.to_string(),
+                    other => bail!(ArvoreError::ConfigError(format!(
+                        "unknown placeholder {{{other}}} in opener '{name}'"
+                    ))),
+                })
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut cmd = Command::new(&opener.command);
+    cmd.args(&args).current_dir(path);
+    let failed = |e: String| ArvoreError::OpenFailed(name.to_string(), e);
+
+    if opener.detach {
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
             .spawn()
-            .map_err(|e| ArvoreError::GitError(format!("failed to open Cursor: {e}")))?;
-        println!("{} Opened in {}", "✓".green().bold(), "Cursor".cyan());
+            .map_err(|e| failed(e.to_string()))?;
+    } else {
+        let status = cmd.status().map_err(|e| failed(e.to_string()))?;
+        if !status.success() {
+            bail!(failed(status.to_string()));
+        }
     }
 
     Ok(())
@@ -1,24 +1,54 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
 use colored::Colorize;
+use serde::Serialize;
 
 use crate::config::Config;
+use crate::error::ArvoreError;
 use crate::git;
+use crate::output::{self, OutputFormat};
+
+#[derive(Serialize)]
+struct WorktreePath {
+    path: PathBuf,
+    exists: bool,
+}
 
-pub fn run(config: &Config, branch: &str) -> Result<()> {
+pub fn run(config: &Config, branch: &str, format: OutputFormat) -> Result<()> {
     git::ensure_repo()?;
 
-    let repo_name = git::repo_name()?;
-    let worktree_path = config.worktree_path(&repo_name, branch);
+    let (worktree_path, exists) = match git::resolve_worktree(branch) {
+        Ok(wt) => (wt.path, true),
+        Err(e)
+            if matches!(
+                e.downcast_ref::<ArvoreError>(),
+                Some(ArvoreError::WorktreeNotFound(_))
+            ) =>
+        {
+            let repo = git::repo_info()?;
+            let path = config.worktree_path(&repo, branch)?;
+            eprintln!(
+                "{} worktree path does not exist yet: {}",
+                "warning:".yellow().bold(),
+                path.display()
+            );
+            (path, false)
+        }
+        Err(e) => return Err(e),
+    };
 
-    if !worktree_path.exists() {
-        eprintln!(
-            "{} worktree path does not exist yet: {}",
-            "warning:".yellow().bold(),
-            worktree_path.display()
-        );
+    if format.is_text() {
+        println!("{}", worktree_path.display());
+        return Ok(());
     }
 
-    println!("{}", worktree_path.display());
-
-    Ok(())
+    output::emit(
+        format,
+        "path",
+        &WorktreePath {
+            path: worktree_path,
+            exists,
+        },
+    )
 }
@@ -2,47 +2,186 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Result};
 use colored::Colorize;
+use serde::Serialize;
 
 use crate::config::Config;
 use crate::error::ArvoreError;
 use crate::git;
+use crate::hooks::{self, HookContext, HookKind};
+use crate::output::{self, OutputFormat};
+use crate::session;
+use crate::shell;
+use crate::trash;
 
-pub fn run(config: &Config, target: &str, force: bool) -> Result<()> {
+pub struct RemoveOptions {
+    /// Passed to `git worktree remove --force`, discarding local changes.
+    pub force: bool,
+    /// Keep going when a `pre_remove` hook fails.
+    pub ignore_hook_failure: bool,
+    pub no_hooks: bool,
+    /// Unlock the worktree first; git won't remove or prune a locked one.
+    pub unlock: bool,
+}
+
+/// A removed worktree, as reported by `rm` and `clean` in JSON output.
+#[derive(Debug, Serialize)]
+pub struct Removed {
+    pub branch: Option<String>,
+    pub path: PathBuf,
+    /// Ref holding the uncommitted changes saved before removal.
+    pub snapshot: Option<String>,
+    /// Trash entry the worktree was moved to.
+    pub trash: Option<String>,
+}
+
+pub fn run(
+    config: &Config,
+    target: &str,
+    force: u8,
+    ignore_hook_failure: bool,
+    no_hooks: bool,
+    format: OutputFormat,
+) -> Result<()> {
     git::ensure_repo()?;
 
-    let worktree_path = resolve_target(config, target)?;
+    let wt = git::resolve_worktree(target)?;
+    let worktree_path = wt.path.clone();
+    let repo = git::repo_info()?;
 
-    let worktrees = git::worktree_list()?;
-    let found = worktrees.iter().any(|wt| wt.path == worktree_path);
-    if !found {
-        bail!(ArvoreError::WorktreeNotFound(target.to_string()));
+    if worktree_path == repo.main_worktree {
+        bail!(ArvoreError::MainWorktree(target.to_string()));
     }
 
-    if !force && worktree_path.exists() && git::is_dirty(&worktree_path)? {
+    // Once to discard changes, twice to remove a locked worktree too.
+    if wt.locked.is_some() && force < 2 {
+        bail!(ArvoreError::LockedWorktree(target.to_string()));
+    }
+    if force == 0 && worktree_path.exists() && git::is_dirty(&worktree_path)? {
         bail!(ArvoreError::DirtyWorktree(target.to_string()));
     }
 
-    git::worktree_remove(&worktree_path, force)?;
-    git::worktree_prune()?;
-
-    cleanup_empty_parents(&worktree_path);
+    let ctx = HookContext {
+        repo: &repo.name,
+        branch: wt.branch.as_deref().unwrap_or(""),
+        worktree_path: &worktree_path,
+        main_worktree: &repo.main_worktree,
+        renamed_from: None,
+    };
+    let opts = RemoveOptions {
+        force: force > 0,
+        ignore_hook_failure,
+        no_hooks,
+        unlock: wt.locked.is_some(),
+    };
+    let trashed = remove_worktree(config, &ctx, &opts)?;
 
-    println!("{} Removed worktree {}", "✓".green().bold(), target.cyan());
+    if format.is_text() {
+        println!("{} Removed worktree {}", "✓".green().bold(), target.cyan());
+        if let Some(id) = &trashed {
+            let name = wt.branch.as_deref().unwrap_or(id);
+            println!("  undo with: arvore restore {name}");
+        }
+    }
 
-    Ok(())
+    output::emit(
+        format,
+        "rm",
+        &Removed {
+            branch: wt.branch,
+            path: worktree_path,
+            snapshot: None,
+            trash: trashed,
+        },
+    )
 }
 
-fn resolve_target(config: &Config, target: &str) -> Result<PathBuf> {
-    let as_path = PathBuf::from(target);
-    if as_path.is_absolute() && as_path.exists() {
-        return Ok(as_path);
+/// Removes a single worktree, running `pre_remove` and `post_remove` hooks
+/// around it. Shared by `rm` and `clean`. When called from inside the
+/// worktree, the shell wrapper is sent back to the main worktree. Returns
+/// the trash entry when the worktree was moved to the trash.
+pub fn remove_worktree(
+    config: &Config,
+    ctx: &HookContext,
+    opts: &RemoveOptions,
+) -> Result<Option<String>> {
+    let run_hooks = !opts.no_hooks;
+    let leaving = shell::cwd_is_inside(ctx.worktree_path);
+
+    if run_hooks && !config.hooks.pre_remove.is_empty() {
+        let dir = if ctx.worktree_path.exists() {
+            ctx.worktree_path
+        } else {
+            ctx.main_worktree
+        };
+        if let Err(e) = hooks::run(HookKind::PreRemove, &config.hooks.pre_remove, ctx, dir) {
+            if !opts.ignore_hook_failure {
+                bail!("{e}; removal aborted (use --ignore-hook-failure to remove anyway)");
+            }
+            eprintln!("{} {e}, removing anyway", "warning:".yellow().bold());
+        }
+    }
+
+    if leaving {
+        std::env::set_current_dir(ctx.main_worktree)?;
+    }
+    if opts.unlock {
+        git::worktree_unlock(ctx.worktree_path)?;
+    }
+    let trashed = if config.trash && ctx.worktree_path.exists() {
+        let branch = Some(ctx.branch).filter(|b| !b.is_empty());
+        let entry = trash::move_in(
+            config,
+            ctx.repo,
+            ctx.main_worktree,
+            branch,
+            ctx.worktree_path,
+        )?;
+        Some(entry.id)
+    } else {
+        git::worktree_remove(ctx.worktree_path, opts.force)?;
+        None
+    };
+    git::worktree_prune()?;
+
+    cleanup_empty_parents(ctx.worktree_path);
+    if leaving {
+        shell::request_cd(ctx.main_worktree)?;
+    }
+
+    // The worktree is already gone, so a failing post_remove hook is only
+    // worth a warning.
+    if run_hooks
+        && !config.hooks.post_remove.is_empty()
+        && let Err(e) = hooks::run(
+            HookKind::PostRemove,
+            &config.hooks.post_remove,
+            ctx,
+            ctx.main_worktree,
+        )
+    {
+        eprintln!("{} {e}", "warning:".yellow().bold());
+    }
+
+    // Last, as arvore may be running inside the very session being killed.
+    // The worktree is gone by now, so a session that won't die is no reason
+    // to report the removal as failed.
+    if config.session.kill_on_remove && !ctx.branch.is_empty() {
+        match session::kill(config, ctx.repo, ctx.branch) {
+            Ok(killed) => {
+                for mux in killed {
+                    eprintln!("→ killed {mux} session for {}", ctx.branch);
+                }
+            }
+            Err(e) => eprintln!("{} {e}", "warning:".yellow().bold()),
+        }
     }
 
-    let repo_name = git::repo_name()?;
-    Ok(config.worktree_path(&repo_name, target))
+    Ok(trashed)
 }
 
-fn cleanup_empty_parents(path: &Path) {
+/// Removes `path`'s parent directory if it is empty, such as a repository's
+/// directory under `worktree_base` once its last worktree is gone.
+pub(crate) fn cleanup_empty_parents(path: &Path) {
     if let Some(parent) = path.parent()
         && parent.exists()
         && let Ok(entries) = std::fs::read_dir(parent)
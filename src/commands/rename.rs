@@ -0,0 +1,179 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::commands::remove;
+use crate::config::Config;
+use crate::error::ArvoreError;
+use crate::git;
+use crate::hooks::{self, HookContext, HookKind};
+use crate::output::{self, OutputFormat};
+use crate::session;
+use crate::shell;
+
+#[derive(Serialize)]
+struct Renamed {
+    old_branch: String,
+    branch: String,
+    old_path: PathBuf,
+    path: PathBuf,
+    upstream: Option<String>,
+    /// Steps after the move that failed without undoing it.
+    warnings: Vec<String>,
+}
+
+pub fn run(
+    config: &Config,
+    old: &str,
+    new: &str,
+    set_upstream: bool,
+    no_hooks: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    git::ensure_repo()?;
+
+    let wt = git::resolve_worktree(old)?;
+    let repo = git::repo_info()?;
+    if wt.path == repo.main_worktree {
+        bail!(ArvoreError::MainWorktree(old.to_string()));
+    }
+    let Some(old_branch) = wt.branch.clone() else {
+        bail!("'{old}' has no branch to rename (detached HEAD)");
+    };
+    if git::branch_exists_locally(new)? {
+        bail!("branch '{new}' already exists");
+    }
+    let new_path = config.worktree_path(&repo, new)?;
+    if new_path.exists() {
+        bail!("{} already exists", new_path.display());
+    }
+
+    let old_upstream = git::upstream(&wt.path);
+    // Where the shell is inside the worktree, to follow it to the new path.
+    let within = shell::cwd_within(&wt.path);
+    if within.is_some() {
+        std::env::set_current_dir(&repo.main_worktree)?;
+    }
+
+    git::rename_branch(&old_branch, new)?;
+    if let Some(parent) = new_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if let Err(e) = git::worktree_move(&wt.path, &new_path) {
+        let _ = git::rename_branch(new, &old_branch);
+        return Err(e);
+    }
+    remove::cleanup_empty_parents(&wt.path);
+    // The old path is gone: send the shell along before anything else can
+    // fail.
+    if let Some(relative) = within {
+        shell::request_cd(&new_path.join(relative))?;
+    }
+
+    if format.is_text() {
+        println!(
+            "{} Renamed {} to {} at {}",
+            "✓".green().bold(),
+            old_branch.yellow(),
+            new.cyan(),
+            new_path.display()
+        );
+    }
+
+    // The rename itself is done, so what follows only warns on failure.
+    let mut warnings = Vec::new();
+    let mut warn = |e: anyhow::Error| {
+        eprintln!("{} {e}", "warning:".yellow().bold());
+        warnings.push(e.to_string());
+    };
+
+    let upstream = if set_upstream {
+        match track(config, old_upstream.as_deref(), new, format) {
+            Ok(upstream) => upstream,
+            Err(e) => {
+                warn(e.context("could not update the upstream"));
+                git::upstream(&new_path)
+            }
+        }
+    } else {
+        if let Some(upstream) = &old_upstream
+            && format.is_text()
+        {
+            println!("  still tracks {upstream} (pass --set-upstream to track the new name)");
+        }
+        old_upstream
+    };
+
+    match session::rename(config, &repo.name, &old_branch, new) {
+        Ok(true) => eprintln!("→ renamed tmux session for {new}"),
+        Ok(false) => {}
+        Err(e) => warn(e.context("could not rename the session")),
+    }
+
+    if !no_hooks && !config.hooks.post_rename.is_empty() {
+        let ctx = HookContext {
+            repo: &repo.name,
+            branch: new,
+            worktree_path: &new_path,
+            main_worktree: &repo.main_worktree,
+            renamed_from: Some((&old_branch, &wt.path)),
+        };
+        if let Err(e) = hooks::run(
+            HookKind::PostRename,
+            &config.hooks.post_rename,
+            &ctx,
+            &new_path,
+        ) {
+            warn(e);
+        }
+    }
+
+    output::emit(
+        format,
+        "mv",
+        &Renamed {
+            old_branch,
+            branch: new.to_string(),
+            old_path: wt.path,
+            path: new_path,
+            upstream,
+            warnings,
+        },
+    )
+}
+
+/// Tracks `<remote>/<branch>` on the remote the branch tracked before, or
+/// the default remote. Without such a branch there the old, now misleading
+/// upstream is dropped.
+fn track(
+    config: &Config,
+    old_upstream: Option<&str>,
+    branch: &str,
+    format: OutputFormat,
+) -> Result<Option<String>> {
+    let remote = old_upstream
+        .and_then(|u| u.split_once('/'))
+        .map_or(config.remote.as_str(), |(remote, _)| remote);
+
+    if git::remote_branch_exists(remote, branch)? {
+        git::fetch_branch(remote, branch)?;
+        let upstream = format!("{remote}/{branch}");
+        git::set_upstream(branch, &upstream)?;
+        if format.is_text() {
+            println!("  now tracks {}", upstream.cyan());
+        }
+        return Ok(Some(upstream));
+    }
+
+    if old_upstream.is_some() {
+        git::unset_upstream(branch)?;
+    }
+    if format.is_text() {
+        println!(
+            "  {remote} has no branch {branch} yet; publish it with: git push -u {remote} {branch}"
+        );
+    }
+    Ok(None)
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::error::ArvoreError;
+use crate::git;
+use crate::output::{self, OutputFormat};
+use crate::shell;
+use crate::trash;
+
+#[derive(Serialize)]
+struct Restored {
+    branch: Option<String>,
+    path: PathBuf,
+    head: String,
+    /// Restored detached at `head` because the branch moved meanwhile.
+    detached: bool,
+}
+
+pub fn run(config: &Config, target: &str, format: OutputFormat) -> Result<()> {
+    git::ensure_repo()?;
+
+    let repo = git::repo_info()?;
+    let entries = trash::list(config, &repo.main_worktree)?;
+    let Some(entry) = entries
+        .iter()
+        .rev()
+        .find(|e| e.id == target || e.branch.as_deref() == Some(target))
+    else {
+        bail!(ArvoreError::NotInTrash(target.to_string()));
+    };
+
+    let detached = trash::restore(config, entry)?;
+
+    if format.is_text() {
+        println!(
+            "{} Restored {} at {}",
+            "✓".green().bold(),
+            entry.name().cyan(),
+            entry.path.display()
+        );
+    }
+    if detached {
+        eprintln!(
+            "{} {} has moved since it was removed; the worktree is detached at {}",
+            "warning:".yellow().bold(),
+            entry.name(),
+            &entry.head[..entry.head.len().min(8)]
+        );
+    }
+    shell::request_cd(&entry.path)?;
+
+    output::emit(
+        format,
+        "restore",
+        &Restored {
+            branch: entry.branch.clone(),
+            path: entry.path.clone(),
+            head: entry.head.clone(),
+            detached,
+        },
+    )
+}
@@ -0,0 +1,9 @@
+use anyhow::Result;
+
+use crate::cli::ShellType;
+use crate::shell;
+
+pub fn run(shell: &ShellType) -> Result<()> {
+    print!("{}", shell::init_script(shell)?);
+    Ok(())
+}
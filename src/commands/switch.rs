@@ -0,0 +1,165 @@
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use dialoguer::FuzzySelect;
+use serde::Serialize;
+
+use crate::age;
+use crate::commands::list::{self, WorktreeStatus};
+use crate::commands::open;
+use crate::config::Config;
+use crate::error::ArvoreError;
+use crate::git::{self, WorktreeInfo};
+use crate::output::{self, OutputFormat};
+use crate::shell;
+
+#[derive(Serialize)]
+struct Switched {
+    branch: Option<String>,
+    path: PathBuf,
+    opened: Vec<String>,
+}
+
+pub fn run(config: &Config, query: Option<&str>, open: bool, format: OutputFormat) -> Result<()> {
+    git::ensure_repo()?;
+
+    let worktrees: Vec<WorktreeInfo> = git::worktree_list()?
+        .into_iter()
+        .filter(|wt| !wt.is_bare)
+        .collect();
+
+    let chosen = match query {
+        Some(q) => match git::find_worktree(&worktrees, q) {
+            Ok(wt) => wt.clone(),
+            Err(_) => pick_fuzzy(config, &worktrees, q)?,
+        },
+        None => pick(config, &worktrees, "")?,
+    };
+
+    let opened = if open || config.switch_open {
+        let repo = git::repo_info()?;
+        open::open_path(
+            config,
+            &repo.name,
+            &chosen.path,
+            chosen.branch.as_deref(),
+            None,
+            format,
+        )?
+    } else {
+        if format.is_text() {
+            println!("{}", chosen.path.display());
+        }
+        shell::request_cd(&chosen.path)?;
+        Vec::new()
+    };
+
+    output::emit(
+        format,
+        "switch",
+        &Switched {
+            branch: chosen.branch,
+            path: chosen.path,
+            opened,
+        },
+    )
+}
+
+/// Goes straight to the worktree when `query` fuzzy-matches only one,
+/// otherwise opens the picker with `query` already typed in.
+fn pick_fuzzy(config: &Config, worktrees: &[WorktreeInfo], query: &str) -> Result<WorktreeInfo> {
+    let matches: Vec<&WorktreeInfo> = worktrees
+        .iter()
+        .filter(|wt| fuzzy_matches(query, &search_text(wt)))
+        .collect();
+    match matches.as_slice() {
+        [] => bail!(ArvoreError::WorktreeNotFound(query.to_string())),
+        [only] => Ok((*only).clone()),
+        _ => pick(config, worktrees, query),
+    }
+}
+
+fn pick(config: &Config, worktrees: &[WorktreeInfo], query: &str) -> Result<WorktreeInfo> {
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        bail!(ArvoreError::NotInteractive(
+            "pick a worktree (pass a branch name instead)".into()
+        ));
+    }
+
+    let statuses = list::collect_all(worktrees, None, config.jobs);
+    let labels = labels(&statuses, age::now());
+
+    let selection = FuzzySelect::new()
+        .with_prompt("Switch to")
+        .items(&labels)
+        .default(0)
+        .with_initial_text(query)
+        .interact_opt()?;
+
+    match selection {
+        Some(i) => Ok(worktrees[i].clone()),
+        None => bail!("no worktree selected"),
+    }
+}
+
+/// What a query is matched against outside the picker: the branch and the
+/// directory name. The full path is left out, since the parent directories
+/// every worktree shares would match almost any query.
+fn search_text(wt: &WorktreeInfo) -> String {
+    let dir = wt.path.file_name().unwrap_or_default().to_string_lossy();
+    format!("{} {dir}", wt.branch.as_deref().unwrap_or_default())
+}
+
+/// Picker rows: branch, dirty marker, age and subject of the last commit,
+/// then the path, aligned in columns.
+fn labels(statuses: &[WorktreeStatus], now: i64) -> Vec<String> {
+    let branches: Vec<&str> = statuses
+        .iter()
+        .map(|s| s.branch.as_deref().unwrap_or("(detached)"))
+        .collect();
+    let width = branches
+        .iter()
+        .map(|b| b.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    statuses
+        .iter()
+        .zip(branches)
+        .map(|(s, branch)| {
+            let dirty = if s.dirty { "[modified]" } else { "" };
+            let (age, subject) = s.last_commit.as_ref().map_or_else(
+                || ("-".to_string(), ""),
+                |c| (age::format(now - c.timestamp), c.subject.as_str()),
+            );
+            format!(
+                "{branch:<width$}  {dirty:<10}  {age:>4}  {subject}  ({})",
+                s.path.display()
+            )
+        })
+        .collect()
+}
+
+/// Case-insensitive subsequence match, so `fauth` finds `feature/auth`.
+fn fuzzy_matches(query: &str, text: &str) -> bool {
+    let mut chars = text.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| chars.any(|c| c == q))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matching() {
+        assert!(fuzzy_matches("fauth", "feature/auth"));
+        assert!(fuzzy_matches("AUTH", "feature/auth"));
+        assert!(fuzzy_matches("", "anything"));
+        assert!(!fuzzy_matches("htua", "feature/auth"));
+        assert!(!fuzzy_matches("login", "feature/auth"));
+    }
+}
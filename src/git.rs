@@ -1,7 +1,9 @@
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use anyhow::{bail, Result};
+use serde::Serialize;
 
 use crate::error::ArvoreError;
 
@@ -11,27 +13,40 @@ pub struct WorktreeInfo {
     pub branch: Option<String>,
     pub head: String,
     pub is_bare: bool,
+    /// `Some(reason)` when locked; the reason may be empty.
+    pub locked: Option<String>,
+    /// `Some(reason)` when `git worktree prune` would remove it, usually
+    /// because its directory is gone.
+    pub prunable: Option<String>,
 }
 
-fn run_git(args: &[&str]) -> Result<String> {
-    let output = Command::new("git")
-        .args(args)
-        .output()
-        .map_err(|e| ArvoreError::GitError(format!("failed to execute git: {e}")))?;
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitInfo {
+    pub sha: String,
+    /// Committer date, seconds since the Unix epoch.
+    pub timestamp: i64,
+    pub subject: String,
+}
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!(ArvoreError::GitError(stderr.trim().to_string()));
+impl WorktreeInfo {
+    /// `branch (path)`, for messages that list several worktrees.
+    pub fn describe(&self) -> String {
+        let branch = self.branch.as_deref().unwrap_or("(detached)");
+        format!("{branch} ({})", self.path.display())
     }
+}
 
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+fn run_git(args: &[&str]) -> Result<String> {
+    output(Command::new("git").args(args))
 }
 
 fn run_git_in(dir: &Path, args: &[&str]) -> Result<String> {
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(dir)
-        .args(args)
+    output(Command::new("git").arg("-C").arg(dir).args(args))
+}
+
+/// Runs `cmd` and returns its trimmed stdout, or its stderr as the error.
+fn output(cmd: &mut Command) -> Result<String> {
+    let output = cmd
         .output()
         .map_err(|e| ArvoreError::GitError(format!("failed to execute git: {e}")))?;
 
@@ -48,9 +63,65 @@ pub fn repo_root() -> Result<PathBuf> {
     Ok(PathBuf::from(out))
 }
 
-pub fn repo_name() -> Result<String> {
-    let root = repo_root()?;
-    let name = root
+/// Everything the config needs to know to pick per-repository settings.
+#[derive(Debug, Clone)]
+pub struct RepoInfo {
+    pub name: String,
+    /// Root of the worktree the command runs in.
+    pub toplevel: PathBuf,
+    pub main_worktree: PathBuf,
+    /// URL of each remote, by remote name.
+    pub remote_urls: BTreeMap<String, String>,
+}
+
+impl RepoInfo {
+    pub fn remote_url(&self, remote: &str) -> Option<&str> {
+        self.remote_urls.get(remote).map(String::as_str)
+    }
+}
+
+pub fn repo_info() -> Result<RepoInfo> {
+    let toplevel = repo_root()?;
+    let main_worktree = main_worktree()?;
+    Ok(RepoInfo {
+        // Named after the main worktree, so it is the same from every worktree.
+        name: dir_name(&main_worktree)?,
+        toplevel,
+        main_worktree,
+        // Exits non-zero when there are no remotes at all.
+        remote_urls: run_git(&["config", "--get-regexp", r"^remote\..*\.url$"])
+            .map(|out| parse_remote_urls(&out))
+            .unwrap_or_default(),
+    })
+}
+
+/// Parses `git config --get-regexp` output for `remote.<name>.url` keys.
+/// Remote names may themselves contain dots.
+fn parse_remote_urls(out: &str) -> BTreeMap<String, String> {
+    out.lines()
+        .filter_map(|l| {
+            let (key, url) = l.split_once(' ')?;
+            let name = key.strip_prefix("remote.")?.strip_suffix(".url")?;
+            Some((name.to_string(), url.to_string()))
+        })
+        .collect()
+}
+
+/// The owner part of a remote URL: `acme` for `git@github.com:acme/app.git`
+/// or `https://github.com/acme/app`, `group/sub` for nested GitLab groups.
+pub fn remote_owner(url: &str) -> Option<String> {
+    let path = if let Some((_, rest)) = url.split_once("://") {
+        rest.split_once('/')?.1
+    } else {
+        url.split_once(':')?.1
+    };
+    let path = path.trim_end_matches('/');
+    let (owner, _repo) = path.rsplit_once('/')?;
+    (!owner.is_empty()).then(|| owner.to_string())
+}
+
+fn dir_name(path: &Path) -> Result<String> {
+    let name = path
         .file_name()
         .ok_or(ArvoreError::GitError("cannot determine repo name".into()))?
         .to_string_lossy()
@@ -71,6 +142,8 @@ pub fn parse_worktree_porcelain(output: &str) -> Vec<WorktreeInfo> {
     let mut head = String::new();
     let mut branch: Option<String> = None;
     let mut is_bare = false;
+    let mut locked: Option<String> = None;
+    let mut prunable: Option<String> = None;
 
     for line in output.lines() {
         if line.is_empty() {
@@ -80,6 +153,8 @@ pub fn parse_worktree_porcelain(output: &str) -> Vec<WorktreeInfo> {
                     branch: branch.take(),
                     head: head.clone(),
                     is_bare,
+                    locked: locked.take(),
+                    prunable: prunable.take(),
                 });
                 head.clear();
                 is_bare = false;
@@ -95,6 +170,14 @@ pub fn parse_worktree_porcelain(output: &str) -> Vec<WorktreeInfo> {
             branch = Some(b.to_string());
         } else if line == "bare" {
             is_bare = true;
+        } else if line == "locked" {
+            locked = Some(String::new());
+        } else if let Some(reason) = line.strip_prefix("locked ") {
+            locked = Some(reason.to_string());
+        } else if line == "prunable" {
+            prunable = Some(String::new());
+        } else if let Some(reason) = line.strip_prefix("prunable ") {
+            prunable = Some(reason.to_string());
         }
     }
 
@@ -104,6 +187,8 @@ pub fn parse_worktree_porcelain(output: &str) -> Vec<WorktreeInfo> {
             branch: branch.take(),
             head,
             is_bare,
+            locked,
+            prunable,
         });
     }
 
@@ -115,6 +200,64 @@ pub fn worktree_list() -> Result<Vec<WorktreeInfo>> {
     Ok(parse_worktree_porcelain(&out))
 }
 
+/// Looks `query` up among the worktrees git knows about, wherever they live.
+pub fn resolve_worktree(query: &str) -> Result<WorktreeInfo> {
+    let worktrees = worktree_list()?;
+    find_worktree(&worktrees, query).cloned()
+}
+
+/// Finds the worktree `query` refers to, trying in order: the exact branch
+/// name or an existing path, the sanitized branch or directory name, a
+/// trailing part of the path, then a HEAD commit prefix. The first kind that
+/// matches anything wins; more than one match of that kind is an error.
+pub fn find_worktree<'a>(worktrees: &'a [WorktreeInfo], query: &str) -> Result<&'a WorktreeInfo> {
+    let candidates: Vec<&WorktreeInfo> = worktrees.iter().filter(|wt| !wt.is_bare).collect();
+    let as_path = Path::new(query).canonicalize().ok();
+    let is_commit_prefix = query.len() >= 4 && query.chars().all(|c| c.is_ascii_hexdigit());
+
+    let tiers: [&dyn Fn(&WorktreeInfo) -> bool; 4] = [
+        &|wt| {
+            wt.branch.as_deref() == Some(query)
+                || as_path
+                    .as_ref()
+                    .is_some_and(|p| wt.path.canonicalize().is_ok_and(|wp| &wp == p))
+        },
+        &|wt| {
+            wt.branch.as_deref().map(|b| b.replace('/', "-")).as_deref() == Some(query)
+                || wt.path.file_name().is_some_and(|n| n == query)
+        },
+        &|wt| !query.is_empty() && wt.path.ends_with(query),
+        &|wt| is_commit_prefix && wt.head.starts_with(query),
+    ];
+
+    for matches_tier in tiers {
+        let matches: Vec<&WorktreeInfo> = candidates
+            .iter()
+            .copied()
+            .filter(|wt| matches_tier(wt))
+            .collect();
+        match matches.as_slice() {
+            [] => continue,
+            [only] => return Ok(only),
+            many => bail!(ArvoreError::AmbiguousWorktree(
+                query.to_string(),
+                many.iter().map(|wt| wt.describe()).collect()
+            )),
+        }
+    }
+
+    bail!(ArvoreError::WorktreeNotFound(query.to_string()))
+}
+
+/// The main worktree is always listed first by `git worktree list`.
+pub fn main_worktree() -> Result<PathBuf> {
+    worktree_list()?
+        .into_iter()
+        .next()
+        .map(|wt| wt.path)
+        .ok_or_else(|| ArvoreError::GitError("no main worktree found".into()).into())
+}
+
 pub fn worktree_add(
     path: &Path,
     branch_arg: &str,
@@ -140,6 +283,28 @@ pub fn worktree_add(
     Ok(())
 }
 
+/// Registers a worktree at `path` without checking out any files: on
+/// `branch`, created at `start` if it no longer exists, or detached at
+/// `start` when there is no branch.
+pub fn worktree_attach(path: &Path, branch: Option<&str>, start: &str) -> Result<()> {
+    let path_str = path.to_string_lossy();
+    let mut args = vec!["worktree", "add", "--no-checkout"];
+    match branch {
+        Some(b) if branch_exists_locally(b)? => args.extend([&*path_str, b]),
+        Some(b) => args.extend(["-b", b, &*path_str, start]),
+        None => args.extend(["--detach", &*path_str, start]),
+    }
+    run_git(&args)?;
+    Ok(())
+}
+
+/// Points the index of the worktree at `path` back at its HEAD, leaving the
+/// files as they are.
+pub fn reset_index(path: &Path) -> Result<()> {
+    run_git_in(path, &["reset", "-q"])?;
+    Ok(())
+}
+
 pub fn worktree_remove(path: &Path, force: bool) -> Result<()> {
     let path_str = path.to_string_lossy();
     let mut args = vec!["worktree", "remove"];
@@ -151,11 +316,101 @@ pub fn worktree_remove(path: &Path, force: bool) -> Result<()> {
     Ok(())
 }
 
+pub fn worktree_lock(path: &Path, reason: Option<&str>) -> Result<()> {
+    let path_str = path.to_string_lossy();
+    let mut args = vec!["worktree", "lock"];
+    if let Some(reason) = reason {
+        args.extend(["--reason", reason]);
+    }
+    args.push(&path_str);
+    run_git(&args)?;
+    Ok(())
+}
+
+pub fn worktree_unlock(path: &Path) -> Result<()> {
+    run_git(&["worktree", "unlock", &path.to_string_lossy()])?;
+    Ok(())
+}
+
+pub fn worktree_move(from: &Path, to: &Path) -> Result<()> {
+    run_git(&[
+        "worktree",
+        "move",
+        &from.to_string_lossy(),
+        &to.to_string_lossy(),
+    ])?;
+    Ok(())
+}
+
+pub fn rename_branch(old: &str, new: &str) -> Result<()> {
+    run_git(&["branch", "-m", old, new])?;
+    Ok(())
+}
+
 pub fn worktree_prune() -> Result<()> {
     run_git(&["worktree", "prune"])?;
     Ok(())
 }
 
+/// Points the worktree at `path` and the repository back at each other
+/// after one of them was moved by hand.
+pub fn worktree_repair(path: &Path) -> Result<()> {
+    run_git(&["worktree", "repair", &path.to_string_lossy()])?;
+    Ok(())
+}
+
+/// The repository's `.git` directory, shared by all its worktrees.
+pub fn common_dir() -> Result<PathBuf> {
+    let out = run_git(&["rev-parse", "--path-format=absolute", "--git-common-dir"])?;
+    Ok(PathBuf::from(out))
+}
+
+/// Where the `.git` file of the linked worktree at `path` says its
+/// administrative files are. `None` if there is no such file.
+pub fn gitdir_file(path: &Path) -> Option<PathBuf> {
+    let contents = std::fs::read_to_string(path.join(".git")).ok()?;
+    parse_gitdir_file(&contents, path)
+}
+
+fn parse_gitdir_file(contents: &str, path: &Path) -> Option<PathBuf> {
+    let gitdir = contents.lines().next()?.strip_prefix("gitdir: ")?;
+    // Relative when written by `worktree add --relative-paths`.
+    Some(normalize(&path.join(gitdir.trim())))
+}
+
+/// Resolves `.` and `..` in `path` without touching the filesystem, for
+/// paths that may no longer exist.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                if !out.pop() {
+                    out.push("..");
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+pub fn local_branches() -> Result<Vec<String>> {
+    let out = run_git(&[
+        "for-each-ref",
+        "--format=%(refname:lstrip=2)",
+        "refs/heads/",
+    ])?;
+    Ok(out.lines().map(str::to_string).collect())
+}
+
+pub fn delete_branch(branch: &str, force: bool) -> Result<()> {
+    let flag = if force { "-D" } else { "-d" };
+    run_git(&["branch", flag, branch])?;
+    Ok(())
+}
+
 pub fn status_porcelain(path: &Path) -> Result<String> {
     run_git_in(path, &["status", "--porcelain"])
 }
@@ -165,47 +420,361 @@ pub fn is_dirty(path: &Path) -> Result<bool> {
     Ok(!status.is_empty())
 }
 
+/// The upstream of the branch checked out at `path`, e.g. `origin/feature`.
+pub fn upstream(path: &Path) -> Option<String> {
+    run_git_in(
+        path,
+        &[
+            "rev-parse",
+            "--abbrev-ref",
+            "--symbolic-full-name",
+            "@{upstream}",
+        ],
+    )
+    .ok()
+    .filter(|u| !u.is_empty())
+}
+
+/// Commits `(ahead, behind)` of HEAD at `path` relative to `other`.
+pub fn ahead_behind(path: &Path, other: &str) -> Result<(usize, usize)> {
+    let range = format!("HEAD...{other}");
+    let out = run_git_in(path, &["rev-list", "--left-right", "--count", &range])?;
+    parse_ahead_behind(&out)
+        .ok_or_else(|| ArvoreError::GitError(format!("unexpected rev-list output: {out}")).into())
+}
+
+fn parse_ahead_behind(out: &str) -> Option<(usize, usize)> {
+    let mut parts = out.split_whitespace();
+    let ahead = parts.next()?.parse().ok()?;
+    let behind = parts.next()?.parse().ok()?;
+    Some((ahead, behind))
+}
+
+pub fn last_commit(path: &Path) -> Option<CommitInfo> {
+    let out = run_git_in(path, &["log", "-1", "--format=%H%x00%ct%x00%s"]).ok()?;
+    parse_commit_line(&out)
+}
+
+fn parse_commit_line(line: &str) -> Option<CommitInfo> {
+    let mut parts = line.splitn(3, '\0');
+    Some(CommitInfo {
+        sha: parts.next()?.to_string(),
+        timestamp: parts.next()?.parse().ok()?,
+        subject: parts.next().unwrap_or_default().to_string(),
+    })
+}
+
+pub fn remote_url(remote: &str) -> Option<String> {
+    run_git(&["remote", "get-url", remote]).ok()
+}
+
+/// Fetches `src` from `remote` without storing it under any local ref and
+/// returns the commit it points at.
+pub fn fetch_commit(remote: &str, src: &str) -> Result<String> {
+    run_git(&["fetch", remote, src])?;
+    run_git(&["rev-parse", "FETCH_HEAD^{commit}"])
+}
+
+/// Number of commits reachable from `tip` but from none of `exclude`.
+/// Refs in `exclude` that don't exist are ignored.
+pub fn count_commits(tip: &str, exclude: &[&str]) -> Result<usize> {
+    let mut args = vec!["rev-list".to_string(), "--count".to_string(), tip.to_string()];
+    args.extend(
+        exclude
+            .iter()
+            .filter(|r| ref_exists(r))
+            .map(|r| format!("^{r}")),
+    );
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let out = run_git(&args)?;
+    out.parse()
+        .map_err(|_| ArvoreError::GitError(format!("unexpected rev-list output: {out}")).into())
+}
+
+/// Points `refname` at `commit`, creating it if needed. A branch must not be
+/// checked out in any worktree.
+pub fn update_ref(refname: &str, commit: &str) -> Result<()> {
+    run_git(&["update-ref", refname, commit])?;
+    Ok(())
+}
+
+/// Commits everything in the worktree at `path`, untracked files included,
+/// on top of its HEAD and points `refname` at the commit. The worktree and
+/// its index are left as they are; ignored files are not saved.
+pub fn snapshot(path: &Path, refname: &str, message: &str) -> Result<String> {
+    // A scratch index, so the worktree's own staged state isn't disturbed.
+    let index = std::env::temp_dir().join(format!("arvore-snapshot-{}", std::process::id()));
+    let tree = snapshot_tree(path, &index);
+    let _ = std::fs::remove_file(&index);
+
+    let commit = run_git_in(
+        path,
+        &[
+            "-c",
+            "user.name=arvore",
+            "-c",
+            "user.email=arvore@localhost",
+            "commit-tree",
+            &tree?,
+            "-p",
+            "HEAD",
+            "-m",
+            message,
+        ],
+    )?;
+    run_git_in(path, &["update-ref", refname, &commit])?;
+    Ok(commit)
+}
+
+fn snapshot_tree(path: &Path, index: &Path) -> Result<String> {
+    let git = |args: &[&str]| {
+        output(
+            Command::new("git")
+                .arg("-C")
+                .arg(path)
+                .args(args)
+                .env("GIT_INDEX_FILE", index),
+        )
+    };
+    git(&["read-tree", "HEAD"])?;
+    git(&["add", "--all"])?;
+    git(&["write-tree"])
+}
+
 pub fn fetch_prune() -> Result<()> {
     run_git(&["fetch", "--prune"])?;
     Ok(())
 }
 
 pub fn merged_branches(main_branch: &str) -> Result<Vec<String>> {
-    let out = run_git(&["branch", "--merged", main_branch])?;
+    // `git branch --merged` decorates branches checked out in other
+    // worktrees with `+ `, so list the refs directly instead.
+    let out = run_git(&[
+        "for-each-ref",
+        "--merged",
+        main_branch,
+        "--format=%(refname:lstrip=2)",
+        "refs/heads/",
+    ])?;
     let branches = out
         .lines()
-        .map(|l| l.trim().trim_start_matches("* ").to_string())
+        .map(|l| l.trim().to_string())
         .filter(|b| !b.is_empty() && b != main_branch)
         .collect();
     Ok(branches)
 }
 
-pub fn remote_branch_exists(branch: &str) -> Result<bool> {
-    let out = run_git(&["ls-remote", "--heads", "origin", branch])?;
+/// How a branch's work reached the base branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeKind {
+    /// The branch tip is an ancestor of the base.
+    Merge,
+    /// Every commit was replayed onto the base, as GitHub's rebase button does.
+    Rebase,
+    /// The branch's combined changes landed as a single commit.
+    Squash,
+}
+
+/// Looks for work from `branch` that reached `base` without a merge commit.
+/// Plain merges are left to `merged_branches`, which checks them all at once.
+pub fn detect_rebase_or_squash(base: &str, branch: &str) -> Result<Option<MergeKind>> {
+    let equivalent = cherry(base, branch)?;
+    if equivalent.is_empty() {
+        return Ok(None);
+    }
+    if equivalent.iter().all(|&e| e) {
+        return Ok(Some(MergeKind::Rebase));
+    }
+
+    // Compare the branch's combined diff with each commit `base` gained
+    // since the two diverged, the way `git cherry` compares commits.
+    let merge_base = run_git(&["merge-base", base, branch])?;
+    let squashed = patch_ids(&["diff", "--no-color", "--no-ext-diff", &merge_base, branch])?;
+    let landed = patch_ids(&[
+        "log",
+        "-p",
+        "--no-color",
+        "--no-ext-diff",
+        "--no-merges",
+        "--format=commit %H",
+        &format!("{merge_base}..{base}"),
+    ])?;
+    Ok(squashed
+        .iter()
+        .any(|id| landed.contains(id))
+        .then_some(MergeKind::Squash))
+}
+
+/// Stable patch-ids of the changes `git <args>` prints, one per commit, or
+/// a single one for a plain diff.
+fn patch_ids(args: &[&str]) -> Result<HashSet<String>> {
+    let mut diff = Command::new("git")
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| ArvoreError::GitError(format!("failed to execute git: {e}")))?;
+    let stdout = diff.stdout.take().expect("stdout is piped");
+    let out = output(Command::new("git").args(["patch-id", "--stable"]).stdin(stdout));
+    let status = diff.wait()?;
+    if !status.success() {
+        bail!(ArvoreError::GitError(format!("git {} failed", args.join(" "))));
+    }
+    Ok(out?
+        .lines()
+        .filter_map(|l| l.split_whitespace().next())
+        .map(str::to_string)
+        .collect())
+}
+
+/// For each commit of `head` missing from `upstream`, oldest first, whether
+/// `upstream` has a patch-equivalent commit.
+fn cherry(upstream: &str, head: &str) -> Result<Vec<bool>> {
+    let out = run_git(&["cherry", upstream, head])?;
+    Ok(parse_cherry(&out))
+}
+
+fn parse_cherry(output: &str) -> Vec<bool> {
+    output
+        .lines()
+        .filter_map(|line| match line.chars().next() {
+            Some('-') => Some(true),
+            Some('+') => Some(false),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Local branches whose upstream was deleted on the remote. Branches that
+/// never had an upstream, such as ones never pushed, are not included.
+pub fn gone_branches() -> Result<HashSet<String>> {
+    let out = run_git(&[
+        "for-each-ref",
+        "--format=%(refname:lstrip=2)\t%(upstream)\t%(upstream:track)",
+        "refs/heads/",
+    ])?;
+    Ok(parse_gone_branches(&out))
+}
+
+fn parse_gone_branches(out: &str) -> HashSet<String> {
+    out.lines()
+        .filter_map(|l| {
+            let mut fields = l.split('\t');
+            let branch = fields.next()?;
+            let upstream = fields.next()?;
+            let track = fields.next()?;
+            (!upstream.is_empty() && track == "[gone]").then(|| branch.to_string())
+        })
+        .collect()
+}
+
+/// Asks `remote` itself, not the possibly stale remote-tracking refs.
+pub fn remote_branch_exists(remote: &str, branch: &str) -> Result<bool> {
+    let out = run_git(&[
+        "ls-remote",
+        "--heads",
+        remote,
+        &format!("refs/heads/{branch}"),
+    ])?;
     Ok(!out.is_empty())
 }
 
+pub fn remotes() -> Result<Vec<String>> {
+    let out = run_git(&["remote"])?;
+    Ok(out.lines().map(str::to_string).collect())
+}
+
+/// Fetches just `branch` from `remote` into its remote-tracking ref.
+pub fn fetch_branch(remote: &str, branch: &str) -> Result<()> {
+    run_git(&[
+        "fetch",
+        remote,
+        &format!("+refs/heads/{branch}:refs/remotes/{remote}/{branch}"),
+    ])?;
+    Ok(())
+}
+
+/// Makes `upstream` (e.g. `origin/feature`) the upstream of `branch`.
+pub fn set_upstream(branch: &str, upstream: &str) -> Result<()> {
+    run_git(&["branch", &format!("--set-upstream-to={upstream}"), branch])?;
+    Ok(())
+}
+
+pub fn unset_upstream(branch: &str) -> Result<()> {
+    run_git(&["branch", "--unset-upstream", branch])?;
+    Ok(())
+}
+
 #[allow(dead_code)]
 pub fn current_branch() -> Result<String> {
     run_git(&["rev-parse", "--abbrev-ref", "HEAD"])
 }
 
-pub fn main_branch() -> Result<String> {
-    if run_git(&["rev-parse", "--verify", "refs/heads/main"]).is_ok() {
-        return Ok("main".to_string());
-    }
-    if run_git(&["rev-parse", "--verify", "refs/heads/master"]).is_ok() {
-        return Ok("master".to_string());
-    }
-    if run_git(&["rev-parse", "--verify", "refs/remotes/origin/main"]).is_ok() {
-        return Ok("main".to_string());
+/// The branch worktrees are compared against. In order: `configured`, the
+/// branch `<remote>/HEAD` points at, `init.defaultBranch`, then `main` or
+/// `master`. Each must exist locally or on `remote` to be picked.
+pub fn main_branch(remote: &str, configured: Option<&str>) -> Result<String> {
+    if let Some(branch) = configured {
+        if !ref_exists(&local_or_remote(remote, branch)) {
+            bail!(ArvoreError::ConfigError(format!(
+                "main_branch '{branch}' exists neither locally nor on {remote}"
+            )));
+        }
+        return Ok(branch.to_string());
     }
-    if run_git(&["rev-parse", "--verify", "refs/remotes/origin/master"]).is_ok() {
-        return Ok("master".to_string());
+
+    let remote_head = run_git(&[
+        "symbolic-ref",
+        "--quiet",
+        "--short",
+        &format!("refs/remotes/{remote}/HEAD"),
+    ])
+    .ok();
+    let default_branch = run_git(&["config", "--get", "init.defaultBranch"]).ok();
+
+    pick_main_branch(remote, remote_head.as_deref(), default_branch.as_deref(), |b| {
+        ref_exists(&local_or_remote(remote, b))
+    })
+    .ok_or_else(|| {
+        ArvoreError::GitError(format!(
+            "cannot detect main branch (tried {remote}/HEAD, init.defaultBranch, main, master); set main_branch in the config"
+        ))
+        .into()
+    })
+}
+
+fn pick_main_branch(
+    remote: &str,
+    remote_head: Option<&str>,
+    default_branch: Option<&str>,
+    exists: impl Fn(&str) -> bool,
+) -> Option<String> {
+    let remote_head = remote_head.and_then(|h| h.strip_prefix(&format!("{remote}/")));
+    [remote_head, default_branch, Some("main"), Some("master")]
+        .into_iter()
+        .flatten()
+        .find(|b| exists(b))
+        .map(str::to_string)
+}
+
+/// `branch` when it exists locally, otherwise its remote-tracking ref.
+pub fn local_or_remote(remote: &str, branch: &str) -> String {
+    if branch_exists_locally(branch).unwrap_or(false) {
+        branch.to_string()
+    } else {
+        format!("{remote}/{branch}")
     }
-    bail!(ArvoreError::GitError(
-        "cannot detect main branch (tried main, master)".into()
-    ));
+}
+
+fn ref_exists(name: &str) -> bool {
+    run_git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{name}^{{commit}}"),
+    ])
+    .is_ok()
 }
 
 pub fn ensure_repo() -> Result<()> {
@@ -215,6 +784,17 @@ pub fn ensure_repo() -> Result<()> {
     Ok(())
 }
 
+/// The commit the local `branch` points at, if it exists.
+pub fn branch_head(branch: &str) -> Option<String> {
+    run_git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("refs/heads/{branch}^{{commit}}"),
+    ])
+    .ok()
+}
+
 pub fn branch_exists_locally(branch: &str) -> Result<bool> {
     match run_git(&["rev-parse", "--verify", &format!("refs/heads/{branch}")]) {
         Ok(_) => Ok(true),
@@ -226,6 +806,45 @@ pub fn branch_exists_locally(branch: &str) -> Result<bool> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_gitdir_file_resolves_relative_paths() {
+        let wt = Path::new("/base/app/feature");
+        assert_eq!(
+            parse_gitdir_file("gitdir: /src/app/.git/worktrees/feature\n", wt),
+            Some(PathBuf::from("/src/app/.git/worktrees/feature"))
+        );
+        assert_eq!(
+            parse_gitdir_file("gitdir: ../../.git/worktrees/feature\n", wt),
+            Some(PathBuf::from("/base/.git/worktrees/feature"))
+        );
+        assert_eq!(parse_gitdir_file("not a gitdir file", wt), None);
+    }
+
+    #[test]
+    fn parse_cherry_marks_equivalent_commits() {
+        let output = "- 1111111111111111111111111111111111111111\n+ 2222222222222222222222222222222222222222\n";
+        assert_eq!(parse_cherry(output), vec![true, false]);
+        assert!(parse_cherry("").is_empty());
+    }
+
+    #[test]
+    fn main_branch_prefers_remote_head() {
+        let exists = |b: &str| ["develop", "main", "trunk"].contains(&b);
+        assert_eq!(
+            pick_main_branch("origin", Some("origin/develop"), Some("trunk"), exists).as_deref(),
+            Some("develop")
+        );
+        assert_eq!(
+            pick_main_branch("origin", None, Some("trunk"), exists).as_deref(),
+            Some("trunk")
+        );
+        assert_eq!(
+            pick_main_branch("origin", Some("origin/gone"), None, exists).as_deref(),
+            Some("main")
+        );
+        assert_eq!(pick_main_branch("origin", None, None, |_| false), None);
+    }
+
     #[test]
     fn parse_single_worktree() {
         let output = "\
@@ -291,12 +910,207 @@ detached
         assert!(!wts[0].is_bare);
     }
 
+    #[test]
+    fn parse_locked_with_and_without_reason() {
+        let output = "\
+worktree /path/to/repo
+HEAD abc123def456
+branch refs/heads/main
+
+worktree /path/to/wt1
+HEAD def456abc789
+branch refs/heads/usb
+locked on the usb drive
+
+worktree /path/to/wt2
+HEAD 123456789abc
+detached
+locked
+";
+        let wts = parse_worktree_porcelain(output);
+        assert_eq!(wts.len(), 3);
+        assert!(wts[0].locked.is_none());
+        assert_eq!(wts[1].locked.as_deref(), Some("on the usb drive"));
+        assert_eq!(wts[2].locked.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn parse_prunable() {
+        let output = "\
+worktree /path/to/repo
+HEAD abc123def456
+branch refs/heads/main
+
+worktree /path/to/gone
+HEAD def456abc789
+branch refs/heads/gone
+prunable gitdir file points to non-existent location
+";
+        let wts = parse_worktree_porcelain(output);
+        assert!(wts[0].prunable.is_none());
+        assert_eq!(
+            wts[1].prunable.as_deref(),
+            Some("gitdir file points to non-existent location")
+        );
+        assert_eq!(wts[1].branch.as_deref(), Some("gone"));
+    }
+
+    #[test]
+    fn parse_gone_branches_needs_a_deleted_upstream() {
+        let out = "\
+main\trefs/remotes/origin/main\t
+merged\trefs/remotes/origin/merged\t[gone]
+ahead\trefs/remotes/origin/ahead\t[ahead 2]
+unpushed\t\t";
+        let branches = parse_gone_branches(out);
+        assert_eq!(branches.len(), 1);
+        assert!(branches.contains("merged"));
+    }
+
+    #[test]
+    fn parse_ahead_behind_counts() {
+        assert_eq!(parse_ahead_behind("3\t5"), Some((3, 5)));
+        assert_eq!(parse_ahead_behind("garbage"), None);
+    }
+
+    #[test]
+    fn parse_commit_line_fields() {
+        let commit = parse_commit_line("abc123\x001700000000\x00fix: handle a\x00b").unwrap();
+        assert_eq!(commit.sha, "abc123");
+        assert_eq!(commit.timestamp, 1_700_000_000);
+        assert_eq!(commit.subject, "fix: handle a\x00b");
+    }
+
     #[test]
     fn parse_empty_output() {
         let wts = parse_worktree_porcelain("");
         assert!(wts.is_empty());
     }
 
+    fn wt(path: &str, branch: Option<&str>, head: &str) -> WorktreeInfo {
+        WorktreeInfo {
+            path: PathBuf::from(path),
+            branch: branch.map(str::to_string),
+            head: head.to_string(),
+            is_bare: false,
+            locked: None,
+            prunable: None,
+        }
+    }
+
+    fn sample() -> Vec<WorktreeInfo> {
+        vec![
+            wt("/src/app", Some("main"), "aaaa1111"),
+            wt("/wt/app/feature-auth", Some("feature/auth"), "bbbb2222"),
+            wt("/wt/app/fix", Some("fix"), "cccc3333"),
+            wt("/old/app/fix-old", Some("bugfix"), "cccc4444"),
+            wt("/wt/app/detached", None, "dddd5555"),
+        ]
+    }
+
+    #[test]
+    fn find_by_exact_branch() {
+        let wts = sample();
+        let found = find_worktree(&wts, "feature/auth").unwrap();
+        assert_eq!(found.path, PathBuf::from("/wt/app/feature-auth"));
+    }
+
+    #[test]
+    fn find_by_sanitized_name() {
+        let wts = sample();
+        let found = find_worktree(&wts, "feature-auth").unwrap();
+        assert_eq!(found.branch.as_deref(), Some("feature/auth"));
+    }
+
+    #[test]
+    fn find_by_directory_name() {
+        let wts = sample();
+        let found = find_worktree(&wts, "fix-old").unwrap();
+        assert_eq!(found.branch.as_deref(), Some("bugfix"));
+    }
+
+    #[test]
+    fn find_by_path_suffix() {
+        let wts = sample();
+        let found = find_worktree(&wts, "old/app/fix-old").unwrap();
+        assert_eq!(found.branch.as_deref(), Some("bugfix"));
+    }
+
+    #[test]
+    fn find_by_head_prefix() {
+        let wts = sample();
+        let found = find_worktree(&wts, "dddd").unwrap();
+        assert_eq!(found.path, PathBuf::from("/wt/app/detached"));
+    }
+
+    #[test]
+    fn exact_branch_beats_directory_name() {
+        let wts = sample();
+        let found = find_worktree(&wts, "fix").unwrap();
+        assert_eq!(found.path, PathBuf::from("/wt/app/fix"));
+    }
+
+    #[test]
+    fn ambiguous_head_prefix_lists_matches() {
+        let wts = sample();
+        let err = find_worktree(&wts, "cccc").unwrap_err().to_string();
+        assert!(err.contains("/wt/app/fix"), "{err}");
+        assert!(err.contains("/old/app/fix-old"), "{err}");
+    }
+
+    #[test]
+    fn short_head_prefix_is_ignored() {
+        let wts = sample();
+        assert!(find_worktree(&wts, "ddd").is_err());
+    }
+
+    #[test]
+    fn unknown_query_is_not_found() {
+        let wts = sample();
+        let err = find_worktree(&wts, "nope").unwrap_err().to_string();
+        assert_eq!(err, "worktree 'nope' not found");
+    }
+
+    #[test]
+    fn remote_owner_scp_style() {
+        assert_eq!(
+            remote_owner("git@github.com:acme/app.git").as_deref(),
+            Some("acme")
+        );
+    }
+
+    #[test]
+    fn remote_owner_https() {
+        assert_eq!(
+            remote_owner("https://github.com/acme/app").as_deref(),
+            Some("acme")
+        );
+    }
+
+    #[test]
+    fn remote_owner_nested_groups() {
+        assert_eq!(
+            remote_owner("ssh://git@gitlab.com/group/sub/app.git").as_deref(),
+            Some("group/sub")
+        );
+    }
+
+    #[test]
+    fn parse_remote_urls_handles_dotted_names() {
+        let urls = parse_remote_urls(
+            "remote.origin.url git@github.com:acme/app.git\n\
+             remote.my.fork.url https://github.com/me/app\n",
+        );
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls["origin"], "git@github.com:acme/app.git");
+        assert_eq!(urls["my.fork"], "https://github.com/me/app");
+    }
+
+    #[test]
+    fn remote_owner_local_path_has_none() {
+        assert_eq!(remote_owner("/srv/git/app.git"), None);
+    }
+
     #[test]
     fn parse_no_trailing_newline() {
         let output = "\
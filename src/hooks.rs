@@ -0,0 +1,90 @@
+use std::fmt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+use crate::error::ArvoreError;
+
+#[derive(Debug, Clone, Copy)]
+pub enum HookKind {
+    PostCreate,
+    PreRemove,
+    PostRemove,
+    PostRename,
+}
+
+impl fmt::Display for HookKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HookKind::PostCreate => "post_create",
+            HookKind::PreRemove => "pre_remove",
+            HookKind::PostRemove => "post_remove",
+            HookKind::PostRename => "post_rename",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What a hook gets to know about the worktree it runs for, exposed as
+/// `ARVORE_*` environment variables.
+pub struct HookContext<'a> {
+    pub repo: &'a str,
+    pub branch: &'a str,
+    pub worktree_path: &'a Path,
+    pub main_worktree: &'a Path,
+    /// Branch and path before `arvore mv`.
+    pub renamed_from: Option<(&'a str, &'a Path)>,
+}
+
+impl HookContext<'_> {
+    pub fn env(&self) -> Vec<(&'static str, &std::ffi::OsStr)> {
+        let mut env = vec![
+            ("ARVORE_REPO", self.repo.as_ref()),
+            ("ARVORE_BRANCH", self.branch.as_ref()),
+            ("ARVORE_WORKTREE_PATH", self.worktree_path.as_os_str()),
+            ("ARVORE_MAIN_WORKTREE", self.main_worktree.as_os_str()),
+        ];
+        if let Some((branch, path)) = self.renamed_from {
+            env.push(("ARVORE_OLD_BRANCH", branch.as_ref()));
+            env.push(("ARVORE_OLD_WORKTREE_PATH", path.as_os_str()));
+        }
+        env
+    }
+}
+
+/// Runs each command through `sh -c` inside `dir`, stopping at the first
+/// failure. Hook output goes to stderr so stdout stays machine-readable.
+pub fn run(kind: HookKind, commands: &[String], ctx: &HookContext, dir: &Path) -> Result<()> {
+    for command in commands {
+        eprintln!(
+            "{} {} {}",
+            "→".cyan().bold(),
+            kind.to_string().dimmed(),
+            command
+        );
+
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(dir)
+            .envs(ctx.env())
+            .env("ARVORE_HOOK", kind.to_string())
+            .stdin(Stdio::null())
+            .stdout(std::io::stderr())
+            .status()
+            .map_err(|e| {
+                ArvoreError::HookFailed(kind.to_string(), command.clone(), e.to_string())
+            })?;
+
+        if !status.success() {
+            bail!(ArvoreError::HookFailed(
+                kind.to_string(),
+                command.clone(),
+                status.to_string()
+            ));
+        }
+    }
+    Ok(())
+}
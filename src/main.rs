@@ -1,14 +1,26 @@
+mod age;
 mod cli;
 mod commands;
 mod config;
 mod error;
+mod files;
 mod git;
+mod hooks;
+mod output;
+mod parallel;
+mod repos;
+mod session;
+mod shell;
+mod trash;
 
 use anyhow::Result;
 use clap::Parser;
 use colored::Colorize;
 
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, TrashCommand};
+use commands::clean::CleanOptions;
+use commands::create::CreateOptions;
+use commands::exec::ExecOptions;
 use config::Config;
 
 fn main() {
@@ -20,36 +32,171 @@ fn main() {
 
 fn run() -> Result<()> {
     let cli = Cli::parse();
+    shell::start_dir();
 
     let config = Config::load(cli.config.as_deref())?;
 
     match &cli.command {
-        Commands::Create { branch, from, open } => {
-            commands::create::run(&config, branch, from.as_deref(), *open)?;
+        Commands::Create {
+            branch,
+            from,
+            pr,
+            remote,
+            open,
+            no_hooks,
+        } => {
+            let opts = CreateOptions {
+                branch: branch.as_deref(),
+                from: from.as_deref(),
+                pr: *pr,
+                remote: remote.as_deref(),
+                open: *open,
+                no_hooks: *no_hooks,
+            };
+            commands::create::run(&config, &opts, cli.format)?;
         }
-        Commands::List { porcelain } => {
-            commands::list::run(*porcelain)?;
+        Commands::Review {
+            number,
+            open,
+            no_hooks,
+        } => {
+            let opts = CreateOptions {
+                branch: None,
+                from: None,
+                pr: Some(*number),
+                remote: None,
+                open: *open,
+                no_hooks: *no_hooks,
+            };
+            commands::create::run(&config, &opts, cli.format)?;
         }
-        Commands::Remove { target, force } => {
-            commands::remove::run(&config, target, *force)?;
+        Commands::List {
+            porcelain,
+            columns,
+            all_repos: false,
+        } => {
+            commands::list::run(&config, *porcelain, columns.as_deref(), cli.format)?;
         }
-        Commands::Open {
-            branch,
-            cursor,
-            warp,
-            all,
+        Commands::List {
+            porcelain,
+            columns,
+            all_repos: true,
         } => {
-            commands::open::run(&config, branch, *cursor, *warp, *all)?;
+            commands::list::run_all_repos(
+                cli.config.as_deref(),
+                &config,
+                *porcelain,
+                columns.as_deref(),
+                cli.format,
+            )?;
+        }
+        Commands::Remove {
+            target,
+            force,
+            ignore_hook_failure,
+            no_hooks,
+        } => {
+            commands::remove::run(
+                &config,
+                target,
+                *force,
+                *ignore_hook_failure,
+                *no_hooks,
+                cli.format,
+            )?;
+        }
+        Commands::Rename {
+            old,
+            new,
+            set_upstream,
+            no_hooks,
+        } => {
+            commands::rename::run(&config, old, new, *set_upstream, *no_hooks, cli.format)?;
+        }
+        Commands::Lock { target, reason } => {
+            commands::lock::lock(target, reason.as_deref(), cli.format)?;
+        }
+        Commands::Unlock { target } => {
+            commands::lock::unlock(target, cli.format)?;
+        }
+        Commands::Open { branch, with } => {
+            commands::open::run(&config, branch, with.as_deref(), cli.format)?;
+        }
+        Commands::Switch { query, open } => {
+            commands::switch::run(&config, query.as_deref(), *open, cli.format)?;
         }
         Commands::Path { branch } => {
-            commands::path::run(&config, branch)?;
+            commands::path::run(&config, branch, cli.format)?;
+        }
+        Commands::Clean {
+            base,
+            dry_run,
+            force,
+            ignore_hook_failure,
+            no_hooks,
+            yes,
+            merged_only,
+            remote_deleted_only,
+            older_than,
+            skip_dirty,
+            all_repos,
+        } => {
+            let opts = CleanOptions {
+                base: base.as_deref(),
+                dry_run: *dry_run,
+                force: *force > 0,
+                include_locked: *force > 1,
+                ignore_hook_failure: *ignore_hook_failure,
+                no_hooks: *no_hooks,
+                yes: *yes,
+                merged_only: *merged_only,
+                remote_deleted_only: *remote_deleted_only,
+                older_than: *older_than,
+                skip_dirty: *skip_dirty,
+            };
+            if *all_repos {
+                commands::clean::run_all_repos(cli.config.as_deref(), &config, &opts, cli.format)?;
+            } else {
+                commands::clean::run(&config, &opts, cli.format)?;
+            }
         }
-        Commands::Clean { dry_run } => {
-            commands::clean::run(*dry_run)?;
+        Commands::Trash { command } => match command {
+            TrashCommand::Ls => commands::trash::list(&config, cli.format)?,
+            TrashCommand::Purge { older_than } => {
+                commands::trash::purge(&config, *older_than, cli.format)?;
+            }
+        },
+        Commands::Restore { target } => {
+            commands::restore::run(&config, target, cli.format)?;
+        }
+        Commands::Doctor { dry_run, yes } => {
+            commands::doctor::run(&config, *dry_run, *yes, cli.format)?;
+        }
+        Commands::Exec {
+            branches,
+            dirty,
+            clean,
+            parallel,
+            command,
+        } => {
+            let opts = ExecOptions {
+                branches: branches.as_deref(),
+                dirty: match (*dirty, *clean) {
+                    (true, _) => Some(true),
+                    (_, true) => Some(false),
+                    _ => None,
+                },
+                parallel: *parallel,
+                command,
+            };
+            commands::exec::run(&config, &opts, cli.format)?;
         }
         Commands::Completions { shell } => {
             commands::completions::run(shell)?;
         }
+        Commands::ShellInit { shell } => {
+            commands::shell_init::run(shell)?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Bumped whenever a field is removed or changes meaning. Adding fields is
+/// not a breaking change.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// One pretty-printed JSON document per command.
+    Json,
+    /// One compact JSON object per line.
+    #[value(name = "json-lines")]
+    JsonLines,
+}
+
+impl OutputFormat {
+    pub fn is_text(self) -> bool {
+        self == OutputFormat::Text
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a, T: Serialize> {
+    schema_version: u32,
+    command: &'a str,
+    #[serde(flatten)]
+    data: &'a T,
+}
+
+#[derive(Serialize)]
+struct Record<'a, T: Serialize> {
+    schema_version: u32,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    #[serde(flatten)]
+    item: &'a T,
+}
+
+/// Prints a command's result. Does nothing in text mode, where commands
+/// print for humans as they go.
+pub fn emit<T: Serialize>(format: OutputFormat, command: &str, data: &T) -> Result<()> {
+    let envelope = Envelope {
+        schema_version: SCHEMA_VERSION,
+        command,
+        data,
+    };
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&envelope)?),
+        OutputFormat::JsonLines => println!("{}", serde_json::to_string(&envelope)?),
+    }
+    Ok(())
+}
+
+/// Prints a list of results: as `{ "<key>": [...] }` for `json`, or as one
+/// `{"type": "<kind>", ...}` object per line for `json-lines`.
+pub fn emit_list<T: Serialize>(
+    format: OutputFormat,
+    command: &str,
+    key: &str,
+    kind: &str,
+    items: &[T],
+) -> Result<()> {
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => {
+            let data = serde_json::json!({ key: items });
+            emit(format, command, &data)?;
+        }
+        OutputFormat::JsonLines => {
+            for item in items {
+                let record = Record {
+                    schema_version: SCHEMA_VERSION,
+                    kind,
+                    item,
+                };
+                println!("{}", serde_json::to_string(&record)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Item {
+        name: &'static str,
+    }
+
+    #[test]
+    fn envelope_carries_version_and_command() {
+        let envelope = Envelope {
+            schema_version: SCHEMA_VERSION,
+            command: "path",
+            data: &Item { name: "x" },
+        };
+        let value = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({ "schema_version": 1, "command": "path", "name": "x" })
+        );
+    }
+
+    #[test]
+    fn record_carries_version_and_type() {
+        let record = Record {
+            schema_version: SCHEMA_VERSION,
+            kind: "worktree",
+            item: &Item { name: "x" },
+        };
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({ "schema_version": 1, "type": "worktree", "name": "x" })
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::config::Config;
+use crate::git::{self, RepoInfo};
+use crate::trash;
+
+/// How deep below `worktree_base` worktrees are looked for; templates like
+/// `{repo}/{branch}` nest them in directories named after branch prefixes.
+const MAX_DEPTH: usize = 4;
+
+/// Where to run git for a repository found under `worktree_base`.
+#[derive(Debug, PartialEq, Eq)]
+struct Found {
+    /// The repository's `.git` directory, which identifies it.
+    common_dir: PathBuf,
+    /// Its main worktree, or a linked one for bare repositories.
+    workdir: PathBuf,
+}
+
+/// Repositories with worktrees under `base`, found through the `.git` files
+/// of those worktrees. Returns a directory to run git in for each.
+pub fn discover(base: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    if base.is_dir() {
+        walk(base, 0, &mut found)?;
+    }
+    found.sort_by(|a, b| a.workdir.cmp(&b.workdir));
+    Ok(found.into_iter().map(|f| f.workdir).collect())
+}
+
+fn walk(dir: &Path, depth: usize, found: &mut Vec<Found>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if (depth == 0 && entry.file_name() == trash::TRASH_DIR) || !path.is_dir() {
+            continue;
+        }
+        if path.join(".git").is_file() {
+            if let Some(repo) = repo_of(&path)
+                && !found.iter().any(|f| f.common_dir == repo.common_dir)
+            {
+                found.push(repo);
+            }
+        } else if depth < MAX_DEPTH {
+            walk(&path, depth + 1, found)?;
+        }
+    }
+    Ok(())
+}
+
+/// The repository the linked worktree at `path` belongs to, from the
+/// `<common dir>/worktrees/<name>` its `.git` file points at.
+fn repo_of(path: &Path) -> Option<Found> {
+    let admin = git::gitdir_file(path)?;
+    let worktrees = admin.parent()?;
+    if worktrees.file_name()? != "worktrees" {
+        return None;
+    }
+    let common_dir = worktrees.parent()?.canonicalize().ok()?;
+    let main = common_dir
+        .parent()
+        .filter(|_| common_dir.file_name().is_some_and(|n| n == ".git"));
+    let workdir = match main {
+        Some(main) => main.to_path_buf(),
+        None => path.canonicalize().ok()?,
+    };
+    Some(Found {
+        common_dir,
+        workdir,
+    })
+}
+
+/// Runs `f` in every repository with worktrees under `worktree_base`, from
+/// that repository and with its per-repository settings. A repository that
+/// fails is reported on stderr and skipped. Returns the results, and how
+/// many repositories failed.
+pub fn for_each<T>(
+    config_path: Option<&Path>,
+    config: &Config,
+    mut f: impl FnMut(&RepoInfo, &Config) -> Result<T>,
+) -> Result<(Vec<T>, usize)> {
+    let mut results = Vec::new();
+    let mut failed = 0;
+    for dir in discover(&config.worktree_base)? {
+        let result = std::env::set_current_dir(&dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| git::repo_info())
+            .and_then(|repo| {
+                let config = Config::load_for(config_path, Some(&repo))?;
+                f(&repo, &config)
+            });
+        match result {
+            Ok(value) => results.push(value),
+            Err(e) => {
+                eprintln!("{} {}: {e:#}", "error:".red().bold(), dir.display());
+                failed += 1;
+            }
+        }
+    }
+    Ok((results, failed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linked_worktree(at: &Path, common_dir: &Path, name: &str) {
+        let admin = common_dir.join("worktrees").join(name);
+        std::fs::create_dir_all(&admin).unwrap();
+        std::fs::create_dir_all(at).unwrap();
+        std::fs::write(at.join(".git"), format!("gitdir: {}\n", admin.display())).unwrap();
+    }
+
+    #[test]
+    fn discover_finds_each_repository_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        let base = root.join("worktrees");
+        let app = root.join("src/.app");
+        let lib = root.join("src/lib.git");
+
+        linked_worktree(&base.join(".app/feature"), &app.join(".git"), "feature");
+        linked_worktree(&base.join(".app/fix/login"), &app.join(".git"), "login");
+        linked_worktree(&base.join("lib/topic"), &lib, "topic");
+        linked_worktree(&base.join(".trash/x/worktree"), &app.join(".git"), "x");
+        std::fs::create_dir_all(base.join(".app/stray")).unwrap();
+        std::fs::write(base.join("notes.txt"), "").unwrap();
+
+        assert_eq!(discover(&base).unwrap(), [app, base.join("lib/topic")]);
+        assert!(discover(&root.join("missing")).unwrap().is_empty());
+    }
+}
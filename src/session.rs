@@ -0,0 +1,394 @@
+use std::io::IsTerminal;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Result};
+use colored::Colorize;
+
+use crate::config::{self, Config, Multiplexer, SessionWindow};
+use crate::error::ArvoreError;
+use crate::output::OutputFormat;
+
+/// The session name for a worktree, from the `session.name` template.
+/// `.` and `:` are replaced because tmux reads them as target separators,
+/// `/` because zellij names a socket file after the session.
+pub fn session_name(config: &Config, repo: &str, branch: &str) -> Result<String> {
+    let name = config::render_template(&config.session.name, |placeholder| {
+        Ok(match placeholder {
+            "repo" => repo.to_string(),
+            "branch" => branch.to_string(),
+            "branch_sanitized" => config.sanitize.apply(branch),
+            other => bail!(ArvoreError::ConfigError(format!(
+                "unknown placeholder {{{other}}} in session.name"
+            ))),
+        })
+    })?;
+    Ok(name.replace(['.', ':', '/'], "_"))
+}
+
+/// Attaches to the worktree's session, creating it with the configured
+/// layout first if it doesn't exist yet.
+pub fn open(
+    config: &Config,
+    mux: Multiplexer,
+    repo: &str,
+    branch: &str,
+    path: &Path,
+    format: OutputFormat,
+) -> Result<()> {
+    let name = session_name(config, repo, branch)?;
+    match mux {
+        Multiplexer::Tmux => open_tmux(config, &name, path, format),
+        Multiplexer::Zellij => open_zellij(config, &name, path, format),
+    }
+}
+
+/// Kills the worktree's session on every multiplexer that has one,
+/// returning the multiplexers it was killed on. A multiplexer that isn't
+/// installed simply has no sessions.
+pub fn kill(config: &Config, repo: &str, branch: &str) -> Result<Vec<Multiplexer>> {
+    let name = session_name(config, repo, branch)?;
+    let mut killed = Vec::new();
+
+    if tmux_has_session(config, &name) {
+        let output = tmux_command(config)
+            .args(["kill-session", "-t", &exact(&name)])
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|e| kill_failed(Multiplexer::Tmux, e.to_string()))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(kill_failed(Multiplexer::Tmux, stderr.trim().to_string()));
+        }
+        killed.push(Multiplexer::Tmux);
+    }
+    if zellij_has_session(&name) {
+        let status = Command::new("zellij")
+            .args(["kill-session", &name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .status()
+            .map_err(|e| kill_failed(Multiplexer::Zellij, e.to_string()))?;
+        if !status.success() {
+            bail!(kill_failed(Multiplexer::Zellij, status.to_string()));
+        }
+        killed.push(Multiplexer::Zellij);
+    }
+
+    Ok(killed)
+}
+
+/// Renames the worktree's tmux session after its branch was renamed.
+/// Returns whether there was one. zellij can only rename the session it
+/// runs in, so its sessions keep their name.
+pub fn rename(config: &Config, repo: &str, old: &str, new: &str) -> Result<bool> {
+    let old_name = session_name(config, repo, old)?;
+    if !tmux_has_session(config, &old_name) {
+        return Ok(false);
+    }
+    let new_name = session_name(config, repo, new)?;
+    tmux(
+        config,
+        &["rename-session", "-t", &exact(&old_name), &new_name],
+    )?;
+    Ok(true)
+}
+
+fn failed(mux: Multiplexer, reason: String) -> ArvoreError {
+    ArvoreError::OpenFailed(mux.to_string(), reason)
+}
+
+fn kill_failed(mux: Multiplexer, reason: String) -> ArvoreError {
+    ArvoreError::SessionKillFailed(mux.to_string(), reason)
+}
+
+/// `=name` makes tmux match the session name exactly rather than as a
+/// prefix.
+fn exact(name: &str) -> String {
+    format!("={name}")
+}
+
+fn tmux_command(config: &Config) -> Command {
+    let mut cmd = Command::new("tmux");
+    if let Some(socket) = &config.session.tmux_socket {
+        cmd.args(["-L", socket]);
+    }
+    cmd
+}
+
+/// Runs a tmux command and returns its trimmed stdout.
+fn tmux(config: &Config, args: &[&str]) -> Result<String> {
+    let output = tmux_command(config)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| failed(Multiplexer::Tmux, e.to_string()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(failed(Multiplexer::Tmux, stderr.trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn tmux_has_session(config: &Config, name: &str) -> bool {
+    tmux_command(config)
+        .args(["has-session", "-t", &exact(name)])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+fn open_tmux(config: &Config, name: &str, path: &Path, format: OutputFormat) -> Result<()> {
+    if !tmux_has_session(config, name) {
+        create_tmux_session(config, name, path)?;
+        if format.is_text() {
+            println!(
+                "{} Created tmux session {}",
+                "✓".green().bold(),
+                name.cyan()
+            );
+        }
+    }
+
+    let target = exact(name);
+    let attach = if inside_tmux_server(config) {
+        "switch-client"
+    } else if std::io::stdin().is_terminal() {
+        "attach-session"
+    } else {
+        // Nothing to attach from; the session is there for later.
+        return Ok(());
+    };
+    let status = tmux_command(config)
+        .args([attach, "-t", &target])
+        .status()
+        .map_err(|e| failed(Multiplexer::Tmux, e.to_string()))?;
+    if !status.success() {
+        bail!(failed(Multiplexer::Tmux, status.to_string()));
+    }
+    Ok(())
+}
+
+/// Whether arvore runs inside a client of the tmux server it talks to. `$TMUX`
+/// starts with that server's socket path, whose file name is the `-L` name.
+fn inside_tmux_server(config: &Config) -> bool {
+    let Ok(var) = std::env::var("TMUX") else {
+        return false;
+    };
+    let socket = var.split(',').next().unwrap_or_default();
+    let expected = config.session.tmux_socket.as_deref().unwrap_or("default");
+    Path::new(socket).file_name().is_some_and(|n| n == expected)
+}
+
+fn create_tmux_session(config: &Config, name: &str, path: &Path) -> Result<()> {
+    let dir = path.to_string_lossy();
+    let windows = &config.session.windows;
+
+    let mut args = vec!["new-session", "-d", "-s", name, "-c", &dir];
+    args.extend(["-P", "-F", "#{session_id} #{pane_id}"]);
+    if let Some(window_name) = windows[0].name.as_deref() {
+        args.extend(["-n", window_name]);
+    }
+    let created = tmux(config, &args)?;
+    let (session_id, pane) = created
+        .split_once(' ')
+        .ok_or_else(|| failed(Multiplexer::Tmux, format!("unexpected output: {created}")))?;
+    fill_tmux_window(config, pane, &windows[0], &dir)?;
+
+    let session_target = format!("{session_id}:");
+    for window in &windows[1..] {
+        let mut args = vec!["new-window", "-t", &session_target, "-c", &dir];
+        args.extend(["-P", "-F", "#{pane_id}"]);
+        if let Some(window_name) = window.name.as_deref() {
+            args.extend(["-n", window_name]);
+        }
+        let pane = tmux(config, &args)?;
+        fill_tmux_window(config, &pane, window, &dir)?;
+    }
+
+    Ok(())
+}
+
+/// Starts the window's command in its first `pane`, then splits off and
+/// starts the remaining panes.
+fn fill_tmux_window(config: &Config, pane: &str, window: &SessionWindow, dir: &str) -> Result<()> {
+    if let Some(command) = window.command.as_deref() {
+        tmux_send(config, pane, command)?;
+    }
+    for command in &window.panes {
+        let split = tmux(
+            config,
+            &[
+                "split-window",
+                "-t",
+                pane,
+                "-c",
+                dir,
+                "-P",
+                "-F",
+                "#{pane_id}",
+            ],
+        )?;
+        tmux_send(config, &split, command)?;
+    }
+    if let Some(layout) = window.layout.as_deref() {
+        tmux(config, &["select-layout", "-t", pane, layout])?;
+    }
+    Ok(())
+}
+
+/// Types `command` into `pane` and presses Enter, so the pane's shell is
+/// still there once the command exits.
+fn tmux_send(config: &Config, pane: &str, command: &str) -> Result<()> {
+    if command.is_empty() {
+        return Ok(());
+    }
+    tmux(config, &["send-keys", "-t", pane, "-l", command])?;
+    tmux(config, &["send-keys", "-t", pane, "Enter"])?;
+    Ok(())
+}
+
+fn zellij_has_session(name: &str) -> bool {
+    Command::new("zellij")
+        .args(["list-sessions", "--short"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|o| {
+            o.status.success()
+                && String::from_utf8_lossy(&o.stdout)
+                    .lines()
+                    .any(|l| l.trim() == name)
+        })
+}
+
+fn open_zellij(config: &Config, name: &str, path: &Path, format: OutputFormat) -> Result<()> {
+    if std::env::var_os("ZELLIJ").is_some() {
+        bail!(failed(
+            Multiplexer::Zellij,
+            "already inside a zellij session; detach first".into()
+        ));
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!(ArvoreError::NotInteractive(
+            "attach to a zellij session".into()
+        ));
+    }
+
+    let status = if zellij_has_session(name) {
+        Command::new("zellij").args(["attach", name]).status()
+    } else {
+        let layout_file =
+            std::env::temp_dir().join(format!("arvore-layout-{}.kdl", std::process::id()));
+        std::fs::write(&layout_file, zellij_layout(&config.session.windows, path))?;
+        if format.is_text() {
+            println!(
+                "{} Starting zellij session {}",
+                "✓".green().bold(),
+                name.cyan()
+            );
+        }
+        let status = Command::new("zellij")
+            .arg("--session")
+            .arg(name)
+            .arg("--layout")
+            .arg(&layout_file)
+            .current_dir(path)
+            .status();
+        let _ = std::fs::remove_file(&layout_file);
+        status
+    }
+    .map_err(|e| failed(Multiplexer::Zellij, e.to_string()))?;
+
+    if !status.success() {
+        bail!(failed(Multiplexer::Zellij, status.to_string()));
+    }
+    Ok(())
+}
+
+/// A KDL layout with one tab per window. Commands run in a shell that is
+/// kept open afterwards, matching what tmux panes do.
+fn zellij_layout(windows: &[SessionWindow], path: &Path) -> String {
+    let pane = |command: Option<&str>| match command.filter(|c| !c.is_empty()) {
+        Some(command) => format!(
+            "pane command=\"sh\" {{ args \"-c\" \"{}\"; }}\n",
+            kdl_escape(&format!("{command}; exec \"${{SHELL:-sh}}\""))
+        ),
+        None => "pane\n".to_string(),
+    };
+
+    let mut kdl = String::from("layout {\n");
+    kdl.push_str(&format!(
+        "    cwd \"{}\"\n",
+        kdl_escape(&path.to_string_lossy())
+    ));
+    kdl.push_str("    default_tab_template {\n");
+    kdl.push_str("        pane size=1 borderless=true { plugin location=\"zellij:tab-bar\"; }\n");
+    kdl.push_str("        children\n");
+    kdl.push_str(
+        "        pane size=2 borderless=true { plugin location=\"zellij:status-bar\"; }\n",
+    );
+    kdl.push_str("    }\n");
+    for window in windows {
+        match window.name.as_deref() {
+            Some(name) => kdl.push_str(&format!("    tab name=\"{}\" {{\n", kdl_escape(name))),
+            None => kdl.push_str("    tab {\n"),
+        }
+        kdl.push_str("        ");
+        kdl.push_str(&pane(window.command.as_deref()));
+        for command in &window.panes {
+            kdl.push_str("        ");
+            kdl.push_str(&pane(Some(command)));
+        }
+        kdl.push_str("    }\n");
+    }
+    kdl.push_str("}\n");
+    kdl
+}
+
+fn kdl_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_name_from_template() {
+        let config = Config::default();
+        assert_eq!(
+            session_name(&config, "app", "feature/v1.2").unwrap(),
+            "app-feature-v1_2"
+        );
+        let mut config = Config::default();
+        config.session.name = "{repo}/{branch}".into();
+        assert_eq!(
+            session_name(&config, "app", "feature/auth").unwrap(),
+            "app_feature_auth"
+        );
+    }
+
+    #[test]
+    fn zellij_layout_has_a_tab_per_window() {
+        let windows = vec![
+            SessionWindow {
+                name: Some("editor".into()),
+                command: Some("nvim".into()),
+                ..SessionWindow::default()
+            },
+            SessionWindow {
+                panes: vec!["npm run \"dev\"".into()],
+                ..SessionWindow::default()
+            },
+        ];
+        let kdl = zellij_layout(&windows, Path::new("/work/app"));
+        assert!(kdl.contains("cwd \"/work/app\""));
+        assert!(kdl.contains("tab name=\"editor\""));
+        assert!(kdl.contains("args \"-c\" \"nvim; exec \\\"${SHELL:-sh}\\\"\""));
+        assert!(kdl.contains("npm run \\\"dev\\\""));
+        assert_eq!(kdl.matches("    tab").count(), 2);
+    }
+}
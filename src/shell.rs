@@ -0,0 +1,135 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{bail, Result};
+
+use crate::cli::ShellType;
+
+/// Set by the `shell-init` wrapper to a temporary file. A command that wants
+/// the calling shell to change directory writes the target path there, and
+/// the wrapper `cd`s into it once arvore exits.
+pub const CD_FILE_ENV: &str = "ARVORE_CD_FILE";
+
+/// Asks the `shell-init` wrapper, when there is one, to `cd` into `path`
+/// after the command finishes. Without the wrapper this does nothing.
+pub fn request_cd(path: &Path) -> Result<()> {
+    if let Some(file) = std::env::var_os(CD_FILE_ENV).filter(|f| !f.is_empty()) {
+        std::fs::write(file, path.as_os_str().as_encoded_bytes())?;
+    }
+    Ok(())
+}
+
+/// True when the process was started from inside `dir`, i.e. the calling
+/// shell is about to lose its working directory if `dir` goes away.
+pub fn cwd_is_inside(dir: &Path) -> bool {
+    cwd_within(dir).is_some()
+}
+
+/// Where the process was started, relative to `dir`, if that is inside it.
+pub fn cwd_within(dir: &Path) -> Option<PathBuf> {
+    let cwd = start_dir().as_ref()?;
+    let dir = dir.canonicalize().ok()?;
+    cwd.strip_prefix(dir).ok().map(Path::to_path_buf)
+}
+
+/// The directory the process was started in, which is the calling shell's.
+/// Recorded on first use; `--all-repos` changes directory afterwards, so
+/// `main` asks for it before anything else runs.
+pub fn start_dir() -> &'static Option<PathBuf> {
+    static START_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    START_DIR.get_or_init(|| {
+        std::env::current_dir()
+            .and_then(|d| d.canonicalize())
+            .ok()
+    })
+}
+
+const POSIX_INIT: &str = r#"# arvore shell integration: lets `arvore create`, `arvore switch` and
+# `arvore rm` change the current directory.
+arvore() {
+    local __arvore_cd __arvore_status
+    __arvore_cd="$(mktemp "${TMPDIR:-/tmp}/arvore-cd.XXXXXX")" || return
+    ARVORE_CD_FILE="$__arvore_cd" command arvore "$@"
+    __arvore_status=$?
+    if [ -s "$__arvore_cd" ]; then
+        cd -- "$(cat -- "$__arvore_cd")" || __arvore_status=$?
+    fi
+    rm -f -- "$__arvore_cd"
+    return $__arvore_status
+}
+"#;
+
+const FISH_INIT: &str = r#"# arvore shell integration: lets `arvore create`, `arvore switch` and
+# `arvore rm` change the current directory.
+function arvore --wraps arvore
+    set -l __arvore_cd (mktemp -t arvore-cd.XXXXXX); or return
+    ARVORE_CD_FILE=$__arvore_cd command arvore $argv
+    set -l __arvore_status $status
+    if test -s $__arvore_cd
+        cd (cat $__arvore_cd); or set __arvore_status $status
+    end
+    rm -f $__arvore_cd
+    return $__arvore_status
+end
+"#;
+
+const POWERSHELL_INIT: &str = r#"# arvore shell integration: lets `arvore create`, `arvore switch` and
+# `arvore rm` change the current directory.
+function arvore {
+    $arvoreCd = [System.IO.Path]::GetTempFileName()
+    $env:ARVORE_CD_FILE = $arvoreCd
+    try {
+        $exe = Get-Command -Name arvore -CommandType Application | Select-Object -First 1
+        & $exe @args
+        $arvoreStatus = $LASTEXITCODE
+    } finally {
+        Remove-Item Env:ARVORE_CD_FILE -ErrorAction SilentlyContinue
+    }
+    $target = Get-Content -Raw -LiteralPath $arvoreCd -ErrorAction SilentlyContinue
+    Remove-Item -LiteralPath $arvoreCd -ErrorAction SilentlyContinue
+    if ($target) {
+        Set-Location -LiteralPath $target
+    }
+    $global:LASTEXITCODE = $arvoreStatus
+}
+"#;
+
+/// The wrapper function to `eval` in the given shell's startup file.
+pub fn init_script(shell: &ShellType) -> Result<&'static str> {
+    Ok(match shell {
+        ShellType::Bash | ShellType::Zsh => POSIX_INIT,
+        ShellType::Fish => FISH_INIT,
+        ShellType::PowerShell => POWERSHELL_INIT,
+        ShellType::Elvish => bail!("shell-init does not support elvish yet"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_script_reads_the_cd_file() {
+        for shell in [
+            ShellType::Bash,
+            ShellType::Zsh,
+            ShellType::Fish,
+            ShellType::PowerShell,
+        ] {
+            assert!(init_script(&shell).unwrap().contains(CD_FILE_ENV));
+        }
+    }
+
+    #[test]
+    fn elvish_is_unsupported() {
+        assert!(init_script(&ShellType::Elvish).is_err());
+    }
+
+    #[test]
+    fn cwd_inside_checks_ancestry() {
+        let cwd = std::env::current_dir().unwrap();
+        assert!(cwd_is_inside(&cwd));
+        assert!(cwd_is_inside(cwd.parent().unwrap()));
+        assert!(!cwd_is_inside(&cwd.join("does-not-exist")));
+    }
+}
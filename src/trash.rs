@@ -0,0 +1,279 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::age;
+use crate::config::Config;
+use crate::files;
+use crate::git;
+
+/// Directory under `worktree_base` that holds removed worktrees.
+pub const TRASH_DIR: &str = ".trash";
+/// Inside an entry: the metadata file, next to the worktree itself.
+const META_FILE: &str = "arvore-trash.json";
+const TREE_DIR: &str = "worktree";
+
+/// A removed worktree, as reported by `trash ls --format json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Directory name of the entry in the trash.
+    #[serde(default)]
+    pub id: String,
+    pub repo: String,
+    /// Main worktree of the repository it belonged to.
+    pub main_worktree: PathBuf,
+    pub branch: Option<String>,
+    /// Commit checked out when it was removed.
+    pub head: String,
+    /// Where the worktree was.
+    pub path: PathBuf,
+    /// Seconds since the Unix epoch.
+    pub removed_at: i64,
+}
+
+impl Entry {
+    /// The branch, or the entry id for detached worktrees.
+    pub fn name(&self) -> &str {
+        self.branch.as_deref().unwrap_or(&self.id)
+    }
+}
+
+pub fn dir(config: &Config) -> PathBuf {
+    config.worktree_base.join(TRASH_DIR)
+}
+
+/// Moves the worktree at `path`, untracked and ignored files included, into
+/// the trash. Git still lists it until `git worktree prune` runs.
+pub fn move_in(
+    config: &Config,
+    repo: &str,
+    main_worktree: &Path,
+    branch: Option<&str>,
+    path: &Path,
+) -> Result<Entry> {
+    let removed_at = age::now();
+    let name = match branch {
+        Some(b) => config.sanitize.apply(b),
+        None => path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+    };
+    let trash = dir(config);
+    std::fs::create_dir_all(&trash)
+        .with_context(|| format!("failed to create {}", trash.display()))?;
+
+    let mut id = format!("{repo}-{name}-{removed_at}");
+    let mut n = 1;
+    while trash.join(&id).exists() {
+        n += 1;
+        id = format!("{repo}-{name}-{removed_at}-{n}");
+    }
+    let entry_dir = trash.join(&id);
+    std::fs::create_dir(&entry_dir)?;
+
+    let entry = Entry {
+        id,
+        repo: repo.to_string(),
+        main_worktree: main_worktree.to_path_buf(),
+        branch: branch.map(str::to_string),
+        head: git::last_commit(path).map(|c| c.sha).unwrap_or_default(),
+        path: path.to_path_buf(),
+        removed_at,
+    };
+
+    if let Err(e) = move_path(path, &entry_dir.join(TREE_DIR)) {
+        let _ = std::fs::remove_dir_all(&entry_dir);
+        bail!(
+            "could not move {} to the trash in {}: {e:#} (set `trash: false` to delete worktrees instead)",
+            path.display(),
+            trash.display()
+        );
+    }
+    std::fs::write(
+        entry_dir.join(META_FILE),
+        serde_json::to_string_pretty(&entry)?,
+    )?;
+
+    Ok(entry)
+}
+
+/// Trash entries of the repository whose main worktree is `main_worktree`,
+/// oldest first. Entries without readable metadata are left out.
+pub fn list(config: &Config, main_worktree: &Path) -> Result<Vec<Entry>> {
+    let trash = dir(config);
+    if !trash.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for dir_entry in std::fs::read_dir(&trash)? {
+        let dir_entry = dir_entry?;
+        let Ok(meta) = std::fs::read_to_string(dir_entry.path().join(META_FILE)) else {
+            continue;
+        };
+        let Ok(mut entry) = serde_json::from_str::<Entry>(&meta) else {
+            continue;
+        };
+        if entry.main_worktree == main_worktree {
+            entry.id = dir_entry.file_name().to_string_lossy().into_owned();
+            entries.push(entry);
+        }
+    }
+    entries.sort_by_key(|e| e.removed_at);
+    Ok(entries)
+}
+
+/// Puts the entry's worktree back where it was and registers it with git
+/// again. Staged changes come back unstaged. If the branch has moved on
+/// since, the worktree comes back detached at its old commit, so its files
+/// don't show up as changes against the new tip; returns whether it did.
+pub fn restore(config: &Config, entry: &Entry) -> Result<bool> {
+    if entry.head.is_empty() {
+        bail!(
+            "{} was not a git worktree; its files are in {}",
+            entry.name(),
+            dir(config).join(&entry.id).join(TREE_DIR).display()
+        );
+    }
+    if entry.path.exists() {
+        bail!(
+            "cannot restore {}: {} already exists",
+            entry.name(),
+            entry.path.display()
+        );
+    }
+    if let Some(parent) = entry.path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let moved = entry
+        .branch
+        .as_deref()
+        .and_then(git::branch_head)
+        .is_some_and(|tip| tip != entry.head);
+    let branch = if moved { None } else { entry.branch.as_deref() };
+    git::worktree_attach(&entry.path, branch, &entry.head)?;
+
+    // The old `.git` file points at administrative files git has since
+    // pruned; the one `worktree add` just wrote replaces it.
+    let tree = dir(config).join(&entry.id).join(TREE_DIR);
+    for item in std::fs::read_dir(&tree)? {
+        let item = item?;
+        if item.file_name() == ".git" {
+            continue;
+        }
+        move_path(&item.path(), &entry.path.join(item.file_name()))?;
+    }
+    git::reset_index(&entry.path)?;
+
+    purge(config, entry)?;
+    Ok(moved)
+}
+
+/// Renames `from` to `to`, or copies and deletes it when they are on
+/// different filesystems, as a `{repo_parent}` worktree and the trash under
+/// `worktree_base` can be.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    move_path_with(from, to, |from, to| std::fs::rename(from, to))
+}
+
+fn move_path_with(
+    from: &Path,
+    to: &Path,
+    rename: impl Fn(&Path, &Path) -> std::io::Result<()>,
+) -> Result<()> {
+    match rename(from, to) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {}
+        result => return Ok(result?),
+    }
+    if let Err(e) = files::copy_any(from, to) {
+        let _ = std::fs::remove_dir_all(to).or_else(|_| std::fs::remove_file(to));
+        return Err(e);
+    }
+    if std::fs::symlink_metadata(from)?.is_dir() {
+        std::fs::remove_dir_all(from)?;
+    } else {
+        std::fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+pub fn purge(config: &Config, entry: &Entry) -> Result<()> {
+    let entry_dir = dir(config).join(&entry.id);
+    std::fs::remove_dir_all(&entry_dir)
+        .with_context(|| format!("failed to delete {}", entry_dir.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_in_list_and_purge() {
+        let base = tempfile::tempdir().unwrap();
+        let config = Config {
+            worktree_base: base.path().to_path_buf(),
+            ..Config::default()
+        };
+        let main = base.path().join("app");
+        let worktree = base.path().join("app-feature");
+        std::fs::create_dir_all(worktree.join("node_modules")).unwrap();
+        std::fs::write(worktree.join("node_modules/dep.js"), "ignored").unwrap();
+
+        let first = move_in(&config, "app", &main, Some("feature/x"), &worktree).unwrap();
+        assert!(!worktree.exists());
+        std::fs::create_dir(&worktree).unwrap();
+        let second = move_in(&config, "app", &main, Some("feature/x"), &worktree).unwrap();
+        assert_ne!(first.id, second.id);
+        assert!(first.id.starts_with("app-feature-x-"));
+
+        let kept = dir(&config).join(&first.id).join(TREE_DIR);
+        assert!(kept.join("node_modules/dep.js").exists());
+
+        let entries = list(&config, &main).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].branch.as_deref(), Some("feature/x"));
+        assert!(
+            list(&config, &base.path().join("other"))
+                .unwrap()
+                .is_empty()
+        );
+
+        purge(&config, &entries[0]).unwrap();
+        assert_eq!(list(&config, &main).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn move_path_copies_across_filesystems() {
+        let tmp = tempfile::tempdir().unwrap();
+        let from = tmp.path().join("wt");
+        std::fs::create_dir_all(from.join("src")).unwrap();
+        std::fs::write(from.join("src/lib.rs"), "fn main() {}").unwrap();
+        std::os::unix::fs::symlink("src/lib.rs", from.join("link")).unwrap();
+        let cross_device = |_: &Path, _: &Path| Err(ErrorKind::CrossesDevices.into());
+
+        let to = tmp.path().join("trash");
+        move_path_with(&from, &to, cross_device).unwrap();
+        assert!(!from.exists());
+        assert_eq!(
+            std::fs::read_to_string(to.join("src/lib.rs")).unwrap(),
+            "fn main() {}"
+        );
+        assert_eq!(
+            std::fs::read_link(to.join("link")).unwrap(),
+            Path::new("src/lib.rs")
+        );
+
+        let file = tmp.path().join("file");
+        move_path_with(&to.join("src/lib.rs"), &file, cross_device).unwrap();
+        assert!(file.exists() && !to.join("src/lib.rs").exists());
+
+        let denied = |_: &Path, _: &Path| Err(ErrorKind::PermissionDenied.into());
+        assert!(move_path_with(&to, &from, denied).is_err());
+        assert!(to.exists() && !from.exists());
+    }
+}
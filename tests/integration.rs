@@ -37,13 +37,17 @@ fn setup_test_repo() -> (tempfile::TempDir, PathBuf) {
 }
 
 fn write_test_config(dir: &Path, worktree_base: &Path) -> PathBuf {
+    write_test_config_with(dir, worktree_base, "")
+}
+
+fn write_test_config_with(dir: &Path, worktree_base: &Path, extra: &str) -> PathBuf {
     let config_path = dir.join("config.yaml");
     let canonical_base = worktree_base
         .canonicalize()
         .unwrap_or_else(|_| worktree_base.to_path_buf());
     std::fs::write(
         &config_path,
-        format!("worktree_base: {}", canonical_base.display()),
+        format!("worktree_base: {}\n{extra}", canonical_base.display()),
     )
     .unwrap();
     config_path
@@ -192,6 +196,131 @@ fn rm_dirty_worktree_without_force_fails() {
     );
 }
 
+#[test]
+fn rm_moves_worktree_to_trash_and_restore_brings_it_back() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let wt_path = wt_base
+        .path()
+        .join(repo.file_name().unwrap())
+        .join("feature");
+
+    let output = run_arvore(&repo, &config, &["create", "feature", "--from", "main"]);
+    assert!(output.status.success());
+    std::fs::write(repo.join(".git/info/exclude"), "build/\n").unwrap();
+    std::fs::create_dir(wt_path.join("build")).unwrap();
+    std::fs::write(wt_path.join("build/out.o"), "ignored").unwrap();
+    std::fs::write(wt_path.join("notes.txt"), "untracked").unwrap();
+
+    let removed = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "rm", "feature", "--force"],
+    ));
+    assert!(removed["trash"].is_string());
+    assert!(!wt_path.exists());
+
+    let trash = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "trash", "ls"],
+    ));
+    assert_eq!(trash["entries"][0]["branch"], "feature");
+    assert_eq!(trash["entries"][0]["path"], wt_path.to_str().unwrap());
+
+    let output = run_arvore(&repo, &config, &["restore", "feature"]);
+    assert!(
+        output.status.success(),
+        "restore failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        std::fs::read_to_string(wt_path.join("build/out.o")).unwrap(),
+        "ignored"
+    );
+    let status = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(&wt_path)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&status.stdout), "?? notes.txt\n");
+    let list = json(&run_arvore(&repo, &config, &["--format", "json", "ls"]));
+    assert!(
+        list["worktrees"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|wt| wt["branch"] == "feature")
+    );
+
+    let output = run_arvore(&repo, &config, &["rm", "feature", "--force"]);
+    assert!(output.status.success());
+    let purged = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "trash", "purge", "--older-than", "1d"],
+    ));
+    assert!(purged["purged"].as_array().unwrap().is_empty());
+    let purged = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "trash", "purge"],
+    ));
+    assert_eq!(purged["purged"].as_array().unwrap().len(), 1);
+
+    let output = run_arvore(&repo, &config, &["restore", "feature"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("nothing in the trash"), "stderr: {stderr}");
+}
+
+#[test]
+fn restore_detaches_when_branch_moved_meanwhile() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let wt_path = wt_base
+        .path()
+        .join(repo.file_name().unwrap())
+        .join("moving");
+
+    let output = run_arvore(&repo, &config, &["create", "moving", "--from", "main"]);
+    assert!(output.status.success());
+    std::fs::write(wt_path.join("a.txt"), "v1").unwrap();
+    git(&wt_path, &["add", "a.txt"]);
+    git(&wt_path, &["commit", "-q", "-m", "v1"]);
+    let output = run_arvore(&repo, &config, &["rm", "moving"]);
+    assert!(output.status.success());
+
+    git(&repo, &["checkout", "-q", "moving"]);
+    std::fs::write(repo.join("a.txt"), "v2").unwrap();
+    git(&repo, &["commit", "-q", "-am", "v2"]);
+    git(&repo, &["checkout", "-q", "main"]);
+
+    let output = run_arvore(&repo, &config, &["--format", "json", "restore", "moving"]);
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let restored = json(&output);
+    assert_eq!(restored["detached"], true);
+    assert!(stderr.contains("has moved"), "stderr: {stderr}");
+    assert_eq!(std::fs::read_to_string(wt_path.join("a.txt")).unwrap(), "v1");
+    let status = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(&wt_path)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&status.stdout).is_empty());
+    let head = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(&wt_path)
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&head.stdout).trim(),
+        restored["head"].as_str().unwrap()
+    );
+}
+
 #[test]
 fn completions_zsh_produces_output() {
     let (_dir, repo) = setup_test_repo();
@@ -218,3 +347,1814 @@ fn ls_outside_git_repo_fails() {
     let output = run_arvore(dir.path(), &config, &["ls"]);
     assert!(!output.status.success(), "ls should fail outside git repo");
 }
+
+#[test]
+fn create_runs_post_create_hooks_with_env() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        "hooks:\n  post_create:\n    - echo \"$ARVORE_REPO $ARVORE_BRANCH $ARVORE_WORKTREE_PATH\" > hook.txt\n",
+    );
+
+    let output = run_arvore(&repo, &config, &["create", "hooked", "--from", "main"]);
+    assert!(
+        output.status.success(),
+        "create failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let repo_name = repo.file_name().unwrap().to_string_lossy();
+    let wt_path = wt_base
+        .path()
+        .canonicalize()
+        .unwrap()
+        .join(repo_name.as_ref())
+        .join("hooked");
+    let written = std::fs::read_to_string(wt_path.join("hook.txt")).unwrap();
+    assert_eq!(
+        written.trim(),
+        format!("{repo_name} hooked {}", wt_path.display())
+    );
+}
+
+#[test]
+fn create_failing_hook_rolls_back_worktree() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        "hooks:\n  post_create:\n    - exit 3\n",
+    );
+
+    let output = run_arvore(&repo, &config, &["create", "broken", "--from", "main"]);
+    assert!(
+        !output.status.success(),
+        "create should fail when a hook fails"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("post_create"), "stderr: {stderr}");
+
+    let output = run_arvore(&repo, &config, &["ls", "--porcelain"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("broken"), "worktree left behind: {stdout}");
+
+    let branches = Command::new("git")
+        .args(["branch", "--list", "broken"])
+        .current_dir(&repo)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&branches.stdout).trim().is_empty());
+}
+
+#[test]
+fn create_no_hooks_skips_post_create() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        "hooks:\n  post_create:\n    - exit 1\n",
+    );
+
+    let output = run_arvore(
+        &repo,
+        &config,
+        &["create", "unhooked", "--from", "main", "--no-hooks"],
+    );
+    assert!(
+        output.status.success(),
+        "create failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn rm_runs_pre_and_post_remove_hooks() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let log = _dir.path().join("hooks.log");
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        &format!(
+            "hooks:\n  pre_remove:\n    - echo \"pre $ARVORE_BRANCH $(basename $PWD)\" >> {log}\n  post_remove:\n    - echo \"post $ARVORE_BRANCH\" >> {log}\n",
+            log = log.display()
+        ),
+    );
+
+    let output = run_arvore(&repo, &config, &["create", "torn-down", "--from", "main"]);
+    assert!(output.status.success());
+
+    let output = run_arvore(&repo, &config, &["rm", "torn-down"]);
+    assert!(
+        output.status.success(),
+        "rm failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let written = std::fs::read_to_string(&log).unwrap();
+    assert_eq!(written, "pre torn-down torn-down\npost torn-down\n");
+}
+
+#[test]
+fn mv_renames_branch_moves_worktree_and_tracks_new_name() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let log = _dir.path().join("hooks.log");
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        &format!(
+            "hooks:\n  post_rename:\n    - echo \"$ARVORE_OLD_BRANCH $ARVORE_BRANCH $(basename $PWD)\" >> {log}\n",
+            log = log.display()
+        ),
+    );
+    let _origin = setup_origin(&repo);
+    let base = wt_base.path().join(repo.file_name().unwrap());
+
+    let output = run_arvore(&repo, &config, &["create", "wip", "--from", "main"]);
+    assert!(output.status.success());
+    std::fs::write(base.join("wip/notes.txt"), "keep me").unwrap();
+    git(&base.join("wip"), &["push", "-q", "-u", "origin", "wip"]);
+    git(&repo, &["push", "-q", "origin", "wip:feature/billing"]);
+
+    let moved = json(&run_arvore(
+        &repo,
+        &config,
+        &[
+            "--format",
+            "json",
+            "mv",
+            "wip",
+            "feature/billing",
+            "--set-upstream",
+        ],
+    ));
+    assert_eq!(moved["old_branch"], "wip");
+    assert_eq!(moved["upstream"], "origin/feature/billing");
+    let new_path = base.join("feature-billing");
+    assert_eq!(moved["path"], new_path.to_str().unwrap());
+    assert!(!base.join("wip").exists());
+    assert_eq!(
+        std::fs::read_to_string(new_path.join("notes.txt")).unwrap(),
+        "keep me"
+    );
+
+    let output = Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD@{upstream}"])
+        .current_dir(&new_path)
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "origin/feature/billing"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&log).unwrap(),
+        "wip feature/billing feature-billing\n"
+    );
+
+    let output = run_arvore(&repo, &config, &["mv", "feature/billing", "main"]);
+    assert!(!output.status.success());
+    assert!(new_path.exists());
+}
+
+#[test]
+fn mv_follows_the_shell_even_if_post_rename_hook_fails() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        "hooks:\n  post_rename:\n    - exit 1\n",
+    );
+    let base = wt_base.path().join(repo.file_name().unwrap());
+    let cd_file = _dir.path().join("cd");
+
+    let output = run_arvore(&repo, &config, &["create", "draft", "--from", "main"]);
+    assert!(output.status.success());
+
+    let output = Command::new(arvore_bin())
+        .args(["--config", config.to_str().unwrap()])
+        .args(["--format", "json", "mv", "draft", "final"])
+        .current_dir(base.join("draft"))
+        .env("ARVORE_CD_FILE", &cd_file)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "mv failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let moved = json(&output);
+    let warnings = moved["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].as_str().unwrap().contains("post_rename"));
+    assert_eq!(
+        PathBuf::from(std::fs::read_to_string(&cd_file).unwrap()),
+        base.join("final")
+    );
+}
+
+#[test]
+fn locked_worktrees_are_kept_unless_forced_twice() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let _origin = setup_origin(&repo);
+    let base = wt_base.path().join(repo.file_name().unwrap());
+
+    for branch in ["usb", "gone"] {
+        let output = run_arvore(&repo, &config, &["create", branch, "--from", "main"]);
+        assert!(output.status.success());
+    }
+    let output = run_arvore(
+        &repo,
+        &config,
+        &["lock", "usb", "--reason", "on the usb drive"],
+    );
+    assert!(output.status.success());
+    std::fs::remove_dir_all(base.join("gone")).unwrap();
+
+    let list = json(&run_arvore(&repo, &config, &["--format", "json", "ls"]));
+    let worktrees = list["worktrees"].as_array().unwrap();
+    let find = |name: &str| worktrees.iter().find(|w| w["branch"] == name).unwrap();
+    assert_eq!(find("usb")["locked"], true);
+    assert_eq!(find("usb")["lock_reason"], "on the usb drive");
+    assert_eq!(find("gone")["prunable"], true);
+    let output = run_arvore(&repo, &config, &["ls", "--columns", "branch,status"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[locked]"), "stdout: {stdout}");
+    assert!(stdout.contains("[prunable]"), "stdout: {stdout}");
+
+    // Merged into main, but locked.
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "clean", "--dry-run"],
+    ));
+    let candidates = report["candidates"].as_array().unwrap();
+    assert!(candidates.iter().all(|c| c["branch"] != "usb"));
+
+    for args in [&["rm", "usb"][..], &["rm", "usb", "--force"]] {
+        let output = run_arvore(&repo, &config, args);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("is locked"), "stderr: {stderr}");
+    }
+    let output = run_arvore(&repo, &config, &["rm", "usb", "-ff"]);
+    assert!(
+        output.status.success(),
+        "rm -ff failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let list = json(&run_arvore(&repo, &config, &["--format", "json", "ls"]));
+    assert!(
+        list["worktrees"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|w| w["branch"] != "usb")
+    );
+}
+
+#[test]
+fn doctor_finds_and_fixes_stale_moved_and_orphaned_worktrees() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let base = wt_base
+        .path()
+        .canonicalize()
+        .unwrap()
+        .join(repo.file_name().unwrap());
+
+    for branch in ["gone", "moved", "broken"] {
+        let output = run_arvore(&repo, &config, &["create", branch, "--from", "main"]);
+        assert!(output.status.success());
+    }
+    std::fs::remove_dir_all(base.join("gone")).unwrap();
+    std::fs::rename(base.join("moved"), base.join("moved-by-hand")).unwrap();
+    std::fs::write(
+        base.join("broken/.git"),
+        "gitdir: /nowhere/.git/worktrees/broken\n",
+    )
+    .unwrap();
+    std::fs::create_dir_all(base.join("stray/build")).unwrap();
+    std::fs::write(
+        base.join("stray/.git"),
+        format!("gitdir: {}/.git/worktrees/stray\n", repo.display()),
+    )
+    .unwrap();
+    std::fs::create_dir_all(base.join("notes")).unwrap();
+    git(&repo, &["branch", "loose"]);
+    // Unmerged work is never offered for deletion.
+    git(&repo, &["checkout", "-q", "-b", "unmerged"]);
+    std::fs::write(repo.join("wip.txt"), "wip").unwrap();
+    git(&repo, &["add", "wip.txt"]);
+    git(&repo, &["commit", "-q", "-m", "wip"]);
+    git(&repo, &["checkout", "-q", "main"]);
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "doctor", "--dry-run"],
+    ));
+    let issues = report["issues"].as_array().unwrap();
+    let kinds: Vec<&str> = issues.iter().map(|i| i["kind"].as_str().unwrap()).collect();
+    assert_eq!(
+        kinds,
+        [
+            "moved",
+            "broken_link",
+            "prunable",
+            "orphaned",
+            "unused_branch",
+            "unused_branch"
+        ]
+    );
+    assert_eq!(issues[0]["branch"], "moved");
+    assert_eq!(issues[2]["branch"], "gone");
+    // Free once its prunable worktree is pruned.
+    assert_eq!(issues[4]["branch"], "gone");
+    assert_eq!(issues[5]["branch"], "loose");
+
+    let output = run_arvore(&repo, &config, &["doctor"]);
+    assert!(!output.status.success());
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "doctor", "--yes"],
+    ));
+    assert_eq!(report["fixed"].as_array().unwrap().len(), 6);
+    assert!(report["failed"].as_array().unwrap().is_empty());
+
+    let list = json(&run_arvore(&repo, &config, &["--format", "json", "ls"]));
+    let worktrees = list["worktrees"].as_array().unwrap();
+    assert!(worktrees.iter().all(|w| w["branch"] != "gone"));
+    let moved = worktrees.iter().find(|w| w["branch"] == "moved").unwrap();
+    assert_eq!(
+        moved["path"],
+        base.join("moved-by-hand").to_string_lossy().as_ref()
+    );
+    git(&base.join("broken"), &["status"]);
+    assert!(!base.join("stray").exists());
+    assert!(base.join("notes").exists());
+    git(&repo, &["rev-parse", "--verify", "-q", "refs/heads/unmerged"]);
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "doctor", "--dry-run"],
+    ));
+    assert!(report["issues"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn doctor_leaves_main_clone_under_worktree_base_alone() {
+    let (_dir, _) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let repo = wt_base.path().canonicalize().unwrap().join("repo");
+    std::fs::create_dir_all(repo.join("src")).unwrap();
+    std::fs::write(repo.join("src/lib.rs"), "").unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+    git(&repo, &["config", "user.email", "test@test.com"]);
+    git(&repo, &["config", "user.name", "Test"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "-q", "-m", "init"]);
+
+    let output = run_arvore(&repo, &config, &["create", "feature"]);
+    assert!(output.status.success());
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "doctor", "--yes"],
+    ));
+    assert!(
+        report["issues"].as_array().unwrap().is_empty(),
+        "report: {report}"
+    );
+    assert!(repo.join(".git").is_dir());
+    assert!(repo.join("src/lib.rs").exists());
+    git(&repo, &["status"]);
+}
+
+#[test]
+fn doctor_relinks_moved_worktree_with_relative_gitdir() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let base = wt_base
+        .path()
+        .canonicalize()
+        .unwrap()
+        .join(repo.file_name().unwrap());
+
+    let output = run_arvore(&repo, &config, &["create", "moved", "--from", "main"]);
+    assert!(output.status.success());
+    // What `git worktree add --relative-paths` writes.
+    let admin = repo.canonicalize().unwrap().join(".git/worktrees/moved");
+    let up = "../".repeat(base.join("moved").components().count() - 1);
+    std::fs::write(
+        base.join("moved/.git"),
+        format!("gitdir: {up}{}\n", admin.strip_prefix("/").unwrap().display()),
+    )
+    .unwrap();
+    let up = "../".repeat(admin.components().count() - 1);
+    std::fs::write(
+        admin.join("gitdir"),
+        format!(
+            "{up}{}\n",
+            base.join("moved/.git").strip_prefix("/").unwrap().display()
+        ),
+    )
+    .unwrap();
+    std::fs::rename(base.join("moved"), base.join("moved-by-hand")).unwrap();
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "doctor", "--dry-run"],
+    ));
+    let issues = report["issues"].as_array().unwrap();
+    let moved = issues
+        .iter()
+        .find(|i| i["kind"] == "moved")
+        .unwrap_or_else(|| panic!("moved worktree is found: {report}"));
+    assert_eq!(moved["branch"], "moved");
+    assert_eq!(moved["fix"], "repair");
+    assert!(issues.iter().all(|i| i["kind"] != "orphaned"));
+}
+
+#[test]
+fn rm_failing_pre_remove_hook_aborts_unless_forced() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        "hooks:\n  pre_remove:\n    - exit 1\n",
+    );
+
+    let output = run_arvore(&repo, &config, &["create", "guarded", "--from", "main"]);
+    assert!(output.status.success());
+
+    let output = run_arvore(&repo, &config, &["rm", "guarded"]);
+    assert!(!output.status.success(), "rm should abort on hook failure");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("pre_remove"), "stderr: {stderr}");
+
+    let output = run_arvore(&repo, &config, &["ls", "--porcelain"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("guarded"));
+
+    let output = run_arvore(&repo, &config, &["rm", "guarded", "--force"]);
+    assert!(!output.status.success(), "--force alone overrode the hook");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--ignore-hook-failure"), "stderr: {stderr}");
+
+    let output = run_arvore(&repo, &config, &["rm", "guarded", "--ignore-hook-failure"]);
+    assert!(
+        output.status.success(),
+        "rm --ignore-hook-failure failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = run_arvore(&repo, &config, &["ls", "--porcelain"]);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("guarded"));
+
+    let output = run_arvore(&repo, &config, &["create", "fenced", "--from", "main"]);
+    assert!(output.status.success());
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "clean", "--yes", "--force"],
+    ));
+    assert_eq!(report["failed"][0]["branch"], "fenced");
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "clean", "--yes", "--ignore-hook-failure"],
+    ));
+    assert_eq!(report["removed"][0]["branch"], "fenced");
+}
+
+#[test]
+fn create_copies_and_symlinks_untracked_files() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    std::fs::write(repo.join(".gitignore"), ".env\ncache/\n").unwrap();
+    std::fs::write(repo.join(".env"), "TOKEN=abc").unwrap();
+    std::fs::create_dir_all(repo.join("cache")).unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        "copy:\n  - .env\nsymlink:\n  - cache\n",
+    );
+
+    let output = run_arvore(&repo, &config, &["create", "with-env", "--from", "main"]);
+    assert!(
+        output.status.success(),
+        "create failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("copied 1"), "stdout: {stdout}");
+    assert!(stdout.contains("symlinked 1"), "stdout: {stdout}");
+
+    let repo_name = repo.file_name().unwrap().to_string_lossy();
+    let wt_path = wt_base.path().join(repo_name.as_ref()).join("with-env");
+    assert_eq!(
+        std::fs::read_to_string(wt_path.join(".env")).unwrap(),
+        "TOKEN=abc"
+    );
+    assert!(
+        wt_path
+            .join("cache")
+            .symlink_metadata()
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+}
+
+#[test]
+fn repo_config_file_overrides_global_config() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let hooks = "hooks:\n  post_create:\n    - touch from-global\n";
+    let config = write_test_config_with(_dir.path(), wt_base.path(), hooks);
+    std::fs::write(
+        repo.join(".arvore.yaml"),
+        "hooks:\n  post_create:\n    - touch from-repo\n",
+    )
+    .unwrap();
+    let repo_name = repo.file_name().unwrap().to_string_lossy();
+    let base = wt_base.path().join(repo_name.as_ref());
+
+    // Checked-in hooks only run once the repository is trusted.
+    let output = run_arvore(&repo, &config, &["create", "untrusted", "--from", "main"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("trusted_paths"), "stderr: {stderr}");
+    assert!(base.join("untrusted/from-global").exists());
+    assert!(!base.join("untrusted/from-repo").exists());
+
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        &format!(
+            "{hooks}trusted_paths:\n  - {}\n",
+            repo.canonicalize().unwrap().display()
+        ),
+    );
+    let output = run_arvore(&repo, &config, &["create", "layered", "--from", "main"]);
+    assert!(
+        output.status.success(),
+        "create failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let wt_path = base.join("layered");
+    assert!(wt_path.join("from-repo").exists());
+    assert!(!wt_path.join("from-global").exists());
+}
+
+#[test]
+fn repos_entry_selects_worktree_base_by_name() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let other_base = tempfile::tempdir().unwrap();
+    let repo_name = repo.file_name().unwrap().to_string_lossy().to_string();
+    let other = other_base.path().canonicalize().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        &format!(
+            "repos:\n  {repo_name}:\n    worktree_base: {}\n",
+            other.display()
+        ),
+    );
+
+    let output = run_arvore(&repo, &config, &["path", "feature-x"]);
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    assert_eq!(
+        stdout,
+        other.join(&repo_name).join("feature-x").to_string_lossy()
+    );
+}
+
+#[test]
+fn path_from_inside_a_worktree_uses_main_repo_name() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+
+    let output = run_arvore(&repo, &config, &["create", "inner", "--from", "main"]);
+    assert!(output.status.success());
+
+    let repo_name = repo.file_name().unwrap().to_string_lossy();
+    let canonical_base = wt_base.path().canonicalize().unwrap();
+    let inner = canonical_base.join(repo_name.as_ref()).join("inner");
+
+    let output = run_arvore(&inner, &config, &["path", "other"]);
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    assert_eq!(
+        stdout,
+        canonical_base
+            .join(repo_name.as_ref())
+            .join("other")
+            .to_string_lossy()
+    );
+}
+
+#[test]
+fn path_template_used_by_create_path_and_rm() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        "path_template: \"{repo}/{branch}\"\n",
+    );
+
+    let output = run_arvore(&repo, &config, &["create", "feat/x", "--from", "main"]);
+    assert!(
+        output.status.success(),
+        "create failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let repo_name = repo.file_name().unwrap().to_string_lossy();
+    let expected = wt_base
+        .path()
+        .canonicalize()
+        .unwrap()
+        .join(repo_name.as_ref())
+        .join("feat")
+        .join("x");
+    assert!(expected.is_dir(), "worktree not at {}", expected.display());
+
+    let output = run_arvore(&repo, &config, &["path", "feat/x"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        expected.to_string_lossy()
+    );
+
+    let output = run_arvore(&repo, &config, &["rm", "feat/x"]);
+    assert!(
+        output.status.success(),
+        "rm failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!expected.exists());
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn commands_find_worktrees_created_outside_arvore() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let elsewhere = tempfile::tempdir().unwrap();
+    let manual = elsewhere.path().canonicalize().unwrap().join("by-hand");
+    git(
+        &repo,
+        &["worktree", "add", "-b", "manual", manual.to_str().unwrap()],
+    );
+
+    let output = run_arvore(&repo, &config, &["path", "manual"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        manual.to_string_lossy()
+    );
+
+    let output = run_arvore(&repo, &config, &["path", "by-hand"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        manual.to_string_lossy()
+    );
+
+    let output = run_arvore(&repo, &config, &["rm", "manual"]);
+    assert!(
+        output.status.success(),
+        "rm failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!manual.exists());
+}
+
+#[test]
+fn ambiguous_query_lists_matches() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let a = tempfile::tempdir().unwrap();
+    let b = tempfile::tempdir().unwrap();
+    let first = a.path().join("shared");
+    let second = b.path().join("shared");
+    git(
+        &repo,
+        &["worktree", "add", "-b", "one", first.to_str().unwrap()],
+    );
+    git(
+        &repo,
+        &["worktree", "add", "-b", "two", second.to_str().unwrap()],
+    );
+
+    let output = run_arvore(&repo, &config, &["rm", "shared"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("more than one"), "stderr: {stderr}");
+    assert!(
+        stderr.contains("one (") && stderr.contains("two ("),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn rm_refuses_main_worktree() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+
+    let output = run_arvore(&repo, &config, &["rm", "main"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("main worktree"), "stderr: {stderr}");
+}
+
+fn json(output: &std::process::Output) -> serde_json::Value {
+    assert!(
+        output.status.success(),
+        "command failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap_or_else(|e| {
+        panic!(
+            "invalid JSON ({e}): {}",
+            String::from_utf8_lossy(&output.stdout)
+        )
+    })
+}
+
+/// Adds a bare `origin` remote and pushes `main` to it with upstream
+/// tracking. Keep the returned directory alive for the test's duration.
+fn setup_origin(repo: &Path) -> tempfile::TempDir {
+    let origin = tempfile::tempdir().unwrap();
+    git(origin.path(), &["init", "--bare", "-b", "main"]);
+    git(
+        repo,
+        &["remote", "add", "origin", origin.path().to_str().unwrap()],
+    );
+    git(repo, &["push", "-u", "origin", "main"]);
+    origin
+}
+
+#[test]
+fn ls_json_reports_versioned_status() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let _origin = setup_origin(&repo);
+    git(&repo, &["commit", "--allow-empty", "-m", "unpushed"]);
+
+    let value = json(&run_arvore(&repo, &config, &["ls", "--format", "json"]));
+    assert_eq!(value["schema_version"], 1);
+    assert_eq!(value["command"], "ls");
+    let main = &value["worktrees"][0];
+    assert_eq!(main["branch"], "main");
+    assert_eq!(main["main"], true);
+    // config.yaml lives in the repo, untracked
+    assert_eq!(main["dirty"], true);
+    assert_eq!(main["locked"], false);
+    assert_eq!(main["upstream"], "origin/main");
+    assert_eq!(main["ahead"], 1);
+    assert_eq!(main["behind"], 0);
+    assert_eq!(main["last_commit"]["subject"], "unpushed");
+}
+
+#[test]
+fn ls_json_lines_one_record_per_worktree() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let output = run_arvore(&repo, &config, &["create", "jl", "--from", "main"]);
+    assert!(output.status.success());
+
+    let output = run_arvore(&repo, &config, &["ls", "--format", "json-lines"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let records: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(|r| r["type"] == "worktree"));
+    assert!(records.iter().all(|r| r["schema_version"] == 1));
+    assert_eq!(records[1]["branch"], "jl");
+    assert!(records[1]["upstream"].is_null());
+}
+
+#[test]
+fn create_path_rm_emit_json() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+
+    let created = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "create", "js", "--from", "main"],
+    ));
+    assert_eq!(created["command"], "create");
+    assert_eq!(created["branch"], "js");
+    assert_eq!(created["new_branch"], true);
+    let path = created["path"].as_str().unwrap().to_string();
+
+    let found = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "path", "js"],
+    ));
+    assert_eq!(found["path"], path.as_str());
+    assert_eq!(found["exists"], true);
+
+    let removed = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "rm", "js"],
+    ));
+    assert_eq!(removed["command"], "rm");
+    assert_eq!(removed["branch"], "js");
+    assert_eq!(removed["path"], path.as_str());
+}
+
+#[test]
+fn ls_shows_tracking_columns() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        "ls:\n  columns: [branch, upstream, sync, main]\n",
+    );
+    let _origin = setup_origin(&repo);
+    git(&repo, &["commit", "--allow-empty", "-m", "unpushed"]);
+    let output = run_arvore(&repo, &config, &["create", "topic", "--from", "main"]);
+    assert!(output.status.success());
+    let repo_name = repo.file_name().unwrap().to_string_lossy();
+    let topic = wt_base.path().join(repo_name.as_ref()).join("topic");
+    git(&topic, &["commit", "--allow-empty", "-m", "one"]);
+    git(&topic, &["commit", "--allow-empty", "-m", "two"]);
+
+    let output = run_arvore(&repo, &config, &["ls"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let main_line = stdout.lines().find(|l| l.contains("origin/main")).unwrap();
+    assert!(main_line.contains("↑1"), "main line: {main_line}");
+    let topic_line = stdout.lines().find(|l| l.contains("topic")).unwrap();
+    assert!(topic_line.contains("↑2"), "topic line: {topic_line}");
+    assert!(
+        !stdout.contains("PATH"),
+        "path column not configured: {stdout}"
+    );
+
+    let output = run_arvore(&repo, &config, &["ls", "--columns", "branch,age"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("AGE"), "stdout: {stdout}");
+    assert!(!stdout.contains("origin/main"), "stdout: {stdout}");
+}
+
+#[test]
+fn clean_dry_run_finds_merged_and_remote_deleted() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let _origin = setup_origin(&repo);
+
+    for branch in ["gone", "alive", "merged"] {
+        let output = run_arvore(&repo, &config, &["create", branch, "--from", "main"]);
+        assert!(output.status.success());
+    }
+    let repo_name = repo.file_name().unwrap().to_string_lossy();
+    let base = wt_base.path().join(repo_name.as_ref());
+    git(
+        &base.join("gone"),
+        &["commit", "--allow-empty", "-m", "gone"],
+    );
+    git(
+        &base.join("alive"),
+        &["commit", "--allow-empty", "-m", "alive"],
+    );
+    git(&repo, &["push", "-u", "origin", "gone", "alive"]);
+    git(&repo, &["push", "origin", "--delete", "gone"]);
+    git(&repo, &["push", "-u", "origin", "merged"]);
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "clean", "--dry-run"],
+    ));
+    assert_eq!(report["dry_run"], true);
+    let candidates = report["candidates"].as_array().unwrap();
+    let find = |name: &str| candidates.iter().find(|c| c["branch"] == name);
+
+    let gone = find("gone").expect("remote-deleted branch is a candidate");
+    assert_eq!(gone["remote_deleted"], true);
+    assert_eq!(gone["merged"], false);
+    let merged = find("merged").expect("merged branch is a candidate");
+    assert_eq!(merged["merged"], true);
+    assert_eq!(merged["remote_deleted"], false);
+    assert!(find("alive").is_none());
+}
+
+#[test]
+fn clean_yes_removes_filtered_candidates_without_prompting() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let _origin = setup_origin(&repo);
+    let base = wt_base.path().join(repo.file_name().unwrap());
+
+    for branch in ["gone", "merged", "dirty"] {
+        let output = run_arvore(&repo, &config, &["create", branch, "--from", "main"]);
+        assert!(output.status.success());
+    }
+    git(
+        &base.join("gone"),
+        &["commit", "--allow-empty", "-m", "gone"],
+    );
+    git(&repo, &["push", "-q", "-u", "origin", "gone", "merged", "dirty"]);
+    git(&repo, &["push", "-q", "origin", "--delete", "gone"]);
+    std::fs::write(base.join("dirty").join("scratch.txt"), "wip").unwrap();
+
+    let output = run_arvore(&repo, &config, &["clean"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("not running in a terminal"),
+        "stderr: {stderr}"
+    );
+
+    // Every branch was committed to moments ago.
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &[
+            "--format",
+            "json",
+            "clean",
+            "--dry-run",
+            "--older-than",
+            "1d",
+        ],
+    ));
+    assert!(report["candidates"].as_array().unwrap().is_empty());
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &[
+            "--format",
+            "json",
+            "clean",
+            "--yes",
+            "--merged-only",
+            "--skip-dirty",
+        ],
+    ));
+    let removed: Vec<&str> = report["removed"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["branch"].as_str().unwrap())
+        .collect();
+    assert_eq!(removed, ["merged"]);
+    assert!(!base.join("merged").exists());
+    assert!(base.join("gone").exists());
+    assert!(base.join("dirty").exists());
+}
+
+#[test]
+fn clean_yes_keeps_branches_never_pushed() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let _origin = setup_origin(&repo);
+    let base = wt_base.path().join(repo.file_name().unwrap());
+
+    let output = run_arvore(&repo, &config, &["create", "wip-unpushed", "--from", "main"]);
+    assert!(output.status.success());
+    git(
+        &base.join("wip-unpushed"),
+        &["commit", "--allow-empty", "-m", "wip"],
+    );
+
+    for args in [&["clean", "--yes"][..], &["clean", "--yes", "--remote-deleted-only"]] {
+        let mut args = args.to_vec();
+        args.splice(0..0, ["--format", "json"]);
+        let report = json(&run_arvore(&repo, &config, &args));
+        assert!(
+            report["removed"].as_array().unwrap().is_empty(),
+            "report: {report}"
+        );
+    }
+    assert!(base.join("wip-unpushed").exists());
+}
+
+#[test]
+fn clean_snapshots_dirty_worktrees_before_removing() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let _origin = setup_origin(&repo);
+    let base = wt_base.path().join(repo.file_name().unwrap());
+
+    for branch in ["wip", "spike"] {
+        let output = run_arvore(&repo, &config, &["create", branch, "--from", "main"]);
+        assert!(output.status.success());
+        std::fs::write(base.join(branch).join("README.md"), "edited").unwrap();
+        std::fs::write(base.join(branch).join("notes.txt"), "untracked").unwrap();
+    }
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "clean", "--yes"],
+    ));
+    let removed = report["removed"].as_array().unwrap();
+    assert_eq!(removed.len(), 2);
+    for r in removed {
+        let refname = r["snapshot"].as_str().expect("dirty worktree snapshotted");
+        let branch = r["branch"].as_str().unwrap();
+        assert!(refname.starts_with(&format!("refs/arvore/trash/{branch}/")));
+        let show = |file: &str| {
+            let output = Command::new("git")
+                .args(["show", &format!("{refname}:{file}")])
+                .current_dir(&repo)
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        assert_eq!(show("README.md"), "edited");
+        assert_eq!(show("notes.txt"), "untracked");
+    }
+
+    let config = write_test_config_with(_dir.path(), wt_base.path(), "clean:\n  dirty: confirm\n");
+    let output = run_arvore(&repo, &config, &["create", "careful", "--from", "main"]);
+    assert!(output.status.success());
+    std::fs::write(base.join("careful").join("notes.txt"), "untracked").unwrap();
+
+    // There is no terminal to confirm on, so the changes stay.
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "clean", "--yes"],
+    ));
+    assert!(report["removed"].as_array().unwrap().is_empty());
+    assert_eq!(report["kept"][0]["branch"], "careful");
+    assert!(base.join("careful").join("notes.txt").exists());
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "clean", "--yes", "--force"],
+    ));
+    assert_eq!(report["removed"][0]["branch"], "careful");
+    assert!(report["removed"][0]["snapshot"].is_null());
+    assert!(!base.join("careful").exists());
+}
+
+#[test]
+fn switch_prints_path_of_unique_fuzzy_match() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    for branch in ["feature/auth", "feature/login"] {
+        let output = run_arvore(&repo, &config, &["create", branch, "--from", "main"]);
+        assert!(output.status.success());
+    }
+
+    let output = run_arvore(&repo, &config, &["sw", "fauth"]);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.trim().ends_with("feature-auth"), "stdout: {stdout}");
+
+    let output = run_arvore(&repo, &config, &["--format", "json", "switch", "login"]);
+    assert_eq!(json(&output)["branch"], "feature/login");
+
+    // Several matches need the picker, which can't run without a terminal.
+    let output = run_arvore(&repo, &config, &["switch", "feature"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("not running in a terminal"),
+        "stderr: {stderr}"
+    );
+}
+
+#[test]
+fn shell_init_wrapper_changes_directory() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let bin_dir = arvore_bin().parent().unwrap().to_path_buf();
+    let path = format!(
+        "{}:{}",
+        bin_dir.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let script = format!(
+        r#"eval "$(arvore shell-init bash)"
+arvore --config '{config}' create feature/cd --from main >/dev/null || exit 1
+echo "after create: $PWD"
+arvore --config '{config}' rm feature/cd >/dev/null || exit 1
+echo "after rm: $PWD""#,
+        config = config.display()
+    );
+
+    let output = Command::new("bash")
+        .args(["-c", &script])
+        .current_dir(&repo)
+        .env("PATH", path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let worktree = wt_base
+        .path()
+        .join(repo.file_name().unwrap())
+        .join("feature-cd");
+    let main = repo.canonicalize().unwrap();
+    assert!(
+        stdout.contains(&format!("after create: {}", worktree.display())),
+        "stdout: {stdout}"
+    );
+    assert!(
+        stdout.contains(&format!("after rm: {}", main.display())),
+        "stdout: {stdout}"
+    );
+}
+
+#[test]
+fn open_runs_configured_openers() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        r#"openers:
+  marker:
+    command: sh
+    args: ["-c", 'echo "$1" > opened-with-marker', "sh", "{branch}"]
+    detach: false
+  elsewhere:
+    command: "true"
+    platforms: [plan9]
+open_with: [elsewhere, marker]
+"#,
+    );
+    let output = run_arvore(
+        &repo,
+        &config,
+        &["create", "feature/open", "--from", "main"],
+    );
+    assert!(output.status.success());
+    let worktree = wt_base
+        .path()
+        .join(repo.file_name().unwrap())
+        .join("feature-open");
+
+    // The default list skips openers for other platforms.
+    let output = run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "open", "feature/open"],
+    );
+    assert_eq!(json(&output)["opened"], serde_json::json!(["marker"]));
+    let marker = std::fs::read_to_string(worktree.join("opened-with-marker")).unwrap();
+    assert_eq!(marker.trim(), "feature/open");
+
+    let output = run_arvore(
+        &repo,
+        &config,
+        &["open", "feature/open", "--with", "elsewhere"],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("not available"), "stderr: {stderr}");
+
+    let output = run_arvore(&repo, &config, &["open", "feature/open", "--with", "nope"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unknown opener 'nope'"), "stderr: {stderr}");
+}
+
+/// Runs tmux against a private server so tests never touch the user's.
+fn tmux(socket: &str, args: &[&str]) -> std::process::Output {
+    Command::new("tmux")
+        .args(["-L", socket])
+        .args(args)
+        .env_remove("TMUX")
+        .output()
+        .unwrap()
+}
+
+/// Kills the private tmux server when the test ends, pass or fail.
+struct TmuxServer(String);
+
+impl Drop for TmuxServer {
+    fn drop(&mut self) {
+        tmux(&self.0, &["kill-server"]);
+    }
+}
+
+#[test]
+fn tmux_session_created_with_layout_and_killed_on_rm() {
+    if Command::new("tmux").arg("-V").output().is_err() {
+        eprintln!("tmux not installed, skipping");
+        return;
+    }
+    let socket = format!("arvore-test-{}", std::process::id());
+    let _server = TmuxServer(socket.clone());
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        &format!(
+            r#"session:
+  tmux_socket: {socket}
+  kill_on_remove: true
+  windows:
+    - name: editor
+      command: "echo editing"
+      panes: ["", "echo logs"]
+      layout: even-horizontal
+    - name: server
+"#
+        ),
+    );
+    let output = run_arvore(
+        &repo,
+        &config,
+        &["create", "feature/tmux", "--from", "main"],
+    );
+    assert!(output.status.success());
+    // tmux reads `.` as a separator, so arvore replaces it in session names.
+    let repo_name = repo
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .replace('.', "_");
+    let session = format!("={repo_name}-feature-tmux");
+
+    let output = run_arvore(&repo, &config, &["open", "feature/tmux", "--with", "tmux"]);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let windows = tmux(
+        &socket,
+        &["list-windows", "-t", &session, "-F", "#{window_name}"],
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&windows.stdout),
+        "editor\nserver\n",
+        "{}",
+        String::from_utf8_lossy(&windows.stderr)
+    );
+    let panes = tmux(
+        &socket,
+        &[
+            "list-panes",
+            "-t",
+            &format!("{session}:editor"),
+            "-F",
+            "#{pane_current_path}",
+        ],
+    );
+    let panes = String::from_utf8_lossy(&panes.stdout).to_string();
+    assert_eq!(panes.lines().count(), 3, "panes: {panes}");
+    assert!(
+        panes.lines().all(|p| p.ends_with("feature-tmux")),
+        "panes: {panes}"
+    );
+
+    // Opening again reuses the session rather than failing on the name.
+    let output = run_arvore(&repo, &config, &["open", "feature/tmux", "--with", "tmux"]);
+    assert!(output.status.success());
+
+    let output = run_arvore(&repo, &config, &["rm", "feature/tmux"]);
+    assert!(output.status.success());
+    let has = tmux(&socket, &["has-session", "-t", &session]);
+    assert!(!has.status.success(), "session survived rm");
+}
+
+/// Publishes a commit on top of main under `refname` in `origin`, the way
+/// a forge exposes a pull request's head, without leaving a local branch.
+fn push_request_ref(repo: &Path, refname: &str, file: &str) {
+    git(repo, &["checkout", "-q", "-b", "contributor"]);
+    std::fs::write(repo.join(file), "change").unwrap();
+    git(repo, &["add", file]);
+    git(repo, &["commit", "-q", "-m", "contributor change"]);
+    git(repo, &["push", "-q", "origin", &format!("HEAD:{refname}")]);
+    git(repo, &["checkout", "-q", "main"]);
+    git(repo, &["branch", "-q", "-D", "contributor"]);
+}
+
+#[test]
+fn create_pr_fetches_request_into_local_branch() {
+    let (_dir, repo) = setup_test_repo();
+    let _origin = setup_origin(&repo);
+    push_request_ref(&repo, "refs/pull/7/head", "pr7.txt");
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+
+    let output = run_arvore(&repo, &config, &["--format", "json", "create", "--pr", "7"]);
+    let created = json(&output);
+    assert_eq!(created["branch"], "pr/7");
+    assert_eq!(created["pr"], 7);
+    assert_eq!(created["new_branch"], true);
+    let path = PathBuf::from(created["path"].as_str().unwrap());
+    assert!(path.ends_with("pr-7"));
+    assert!(path.join("pr7.txt").exists());
+
+    let output = run_arvore(&repo, &config, &["create", "--pr", "8"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("could not fetch #8"), "stderr: {stderr}");
+
+    // Reviewing again after the request was force-pushed.
+    let output = run_arvore(&repo, &config, &["rm", "pr/7"]);
+    assert!(output.status.success());
+    git(&repo, &["checkout", "-q", "-b", "rewritten"]);
+    std::fs::write(repo.join("pr7-v2.txt"), "change").unwrap();
+    git(&repo, &["add", "pr7-v2.txt"]);
+    git(&repo, &["commit", "-q", "-m", "rewritten change"]);
+    git(&repo, &["push", "-q", "-f", "origin", "HEAD:refs/pull/7/head"]);
+    git(&repo, &["checkout", "-q", "main"]);
+    let output = run_arvore(&repo, &config, &["create", "--pr", "7"]);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(path.join("pr7-v2.txt").exists());
+    assert!(!path.join("pr7.txt").exists());
+
+    let output = run_arvore(&repo, &config, &["create", "--pr", "7"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("already checked out"), "stderr: {stderr}");
+
+    // Local commits on the review branch are not thrown away.
+    std::fs::write(path.join("fixup.txt"), "mine").unwrap();
+    git(&path, &["add", "fixup.txt"]);
+    git(&path, &["commit", "-q", "-m", "local fixup"]);
+    let output = run_arvore(&repo, &config, &["rm", "pr/7"]);
+    assert!(output.status.success());
+    let output = run_arvore(&repo, &config, &["create", "--pr", "7"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 commit(s)"), "stderr: {stderr}");
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%s", "pr/7"])
+        .current_dir(&repo)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "local fixup");
+
+    // A branch fetched for a worktree that can't be added is not left behind.
+    push_request_ref(&repo, "refs/pull/9/head", "pr9.txt");
+    std::fs::create_dir_all(path.with_file_name("pr-9")).unwrap();
+    std::fs::write(path.with_file_name("pr-9").join("in-the-way"), "").unwrap();
+    let output = run_arvore(&repo, &config, &["create", "--pr", "9"]);
+    assert!(!output.status.success());
+    let output = Command::new("git")
+        .args(["branch", "--list", "pr/9"])
+        .current_dir(&repo)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).trim().is_empty());
+}
+
+#[test]
+fn review_uses_configured_ref_layout() {
+    let (_dir, repo) = setup_test_repo();
+    let _origin = setup_origin(&repo);
+    push_request_ref(&repo, "refs/merge-requests/3/head", "mr3.txt");
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        "review:\n  ref_pattern: refs/merge-requests/{number}/head\n  branch_pattern: mr-{number}\n",
+    );
+
+    let output = run_arvore(&repo, &config, &["review", "3"]);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let path = wt_base.path().join(repo.file_name().unwrap()).join("mr-3");
+    assert!(path.join("mr3.txt").exists());
+}
+
+/// Adds another bare remote named `name` with main pushed to it.
+fn add_remote(repo: &Path, name: &str) -> tempfile::TempDir {
+    let remote = tempfile::tempdir().unwrap();
+    git(remote.path(), &["init", "--bare", "-q", "-b", "main"]);
+    git(
+        repo,
+        &["remote", "add", name, remote.path().to_str().unwrap()],
+    );
+    git(repo, &["push", "-q", name, "main"]);
+    remote
+}
+
+/// Pushes a new branch to `remote` only, leaving nothing local behind.
+fn push_remote_only_branch(repo: &Path, remote: &str, branch: &str) {
+    git(
+        repo,
+        &["push", "-q", remote, &format!("main:refs/heads/{branch}")],
+    );
+}
+
+#[test]
+fn create_checks_out_remote_branch_with_upstream() {
+    let (_dir, repo) = setup_test_repo();
+    let _origin = setup_origin(&repo);
+    let _fork = add_remote(&repo, "fork");
+    let _other = add_remote(&repo, "other");
+    push_remote_only_branch(&repo, "origin", "on-origin");
+    push_remote_only_branch(&repo, "fork", "on-fork");
+    push_remote_only_branch(&repo, "fork", "on-both");
+    push_remote_only_branch(&repo, "other", "on-both");
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+
+    let upstream_of = |path: &str| {
+        let output = Command::new("git")
+            .args(["-C", path, "rev-parse", "--abbrev-ref", "@{upstream}"])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    };
+
+    let created = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "create", "on-origin"],
+    ));
+    assert_eq!(created["remote"], "origin");
+    assert_eq!(created["new_branch"], true);
+    assert_eq!(
+        upstream_of(created["path"].as_str().unwrap()),
+        "origin/on-origin"
+    );
+
+    // Not on the default remote, but on exactly one other.
+    let created = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "create", "on-fork"],
+    ));
+    assert_eq!(created["remote"], "fork");
+    assert_eq!(
+        upstream_of(created["path"].as_str().unwrap()),
+        "fork/on-fork"
+    );
+
+    let output = run_arvore(&repo, &config, &["create", "on-both"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--remote"), "stderr: {stderr}");
+
+    let created = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "create", "on-both", "--remote", "other"],
+    ));
+    assert_eq!(
+        upstream_of(created["path"].as_str().unwrap()),
+        "other/on-both"
+    );
+
+    let output = run_arvore(&repo, &config, &["create", "missing", "--remote", "fork"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("not found on remote 'fork'"),
+        "stderr: {stderr}"
+    );
+
+    git(&repo, &["branch", "local-only"]);
+    let output = run_arvore(&repo, &config, &["create", "local-only", "--remote", "fork"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("already exists locally"), "stderr: {stderr}");
+}
+
+#[test]
+fn create_uses_configured_default_remote() {
+    let (_dir, repo) = setup_test_repo();
+    let _origin = setup_origin(&repo);
+    let _upstream = add_remote(&repo, "upstream");
+    push_remote_only_branch(&repo, "origin", "shared");
+    push_remote_only_branch(&repo, "upstream", "shared");
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(_dir.path(), wt_base.path(), "remote: upstream\n");
+
+    let created = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "create", "shared"],
+    ));
+    assert_eq!(created["remote"], "upstream");
+}
+
+#[test]
+fn clean_detects_base_from_remote_head_and_skips_protected() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config_with(
+        _dir.path(),
+        wt_base.path(),
+        "protected_branches: [\"release/*\"]\n",
+    );
+    let origin = setup_origin(&repo);
+    git(&repo, &["branch", "develop", "main"]);
+    git(&repo, &["push", "-q", "origin", "develop"]);
+    git(
+        origin.path(),
+        &["symbolic-ref", "HEAD", "refs/heads/develop"],
+    );
+    git(&repo, &["remote", "set-head", "origin", "--auto"]);
+
+    for branch in ["feat", "release/1"] {
+        let output = run_arvore(&repo, &config, &["create", branch, "--from", "develop"]);
+        assert!(output.status.success());
+        git(&repo, &["push", "-q", "origin", branch]);
+    }
+    // `feat` gets merged into develop only.
+    let feat_path = wt_base.path().join(repo.file_name().unwrap()).join("feat");
+    git(
+        &feat_path,
+        &["commit", "-q", "--allow-empty", "-m", "feat work"],
+    );
+    git(&feat_path, &["push", "-q", "origin", "feat"]);
+    let develop_wt = tempfile::tempdir().unwrap();
+    let develop_path = develop_wt.path().join("develop");
+    git(
+        &repo,
+        &[
+            "worktree",
+            "add",
+            "-q",
+            develop_path.to_str().unwrap(),
+            "develop",
+        ],
+    );
+    git(&develop_path, &["merge", "-q", "--ff-only", "feat"]);
+    git(&develop_path, &["push", "-q", "origin", "develop"]);
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "clean", "--dry-run"],
+    ));
+    assert_eq!(report["base"], "develop");
+    let branches: Vec<&str> = report["candidates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["branch"].as_str().unwrap())
+        .collect();
+    assert_eq!(branches, vec!["feat"]);
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "clean", "--dry-run", "--base", "main"],
+    ));
+    assert_eq!(report["base"], "main");
+    assert!(
+        report["candidates"].as_array().unwrap().is_empty(),
+        "{report}"
+    );
+}
+
+#[test]
+fn clean_detects_squash_and_rebase_merges() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let _origin = setup_origin(&repo);
+    let base = wt_base.path().join(repo.file_name().unwrap());
+
+    for branch in ["squashed", "rebased", "open"] {
+        let output = run_arvore(&repo, &config, &["create", branch, "--from", "main"]);
+        assert!(output.status.success());
+        for n in 1..=2 {
+            let file = format!("{branch}-{n}.txt");
+            std::fs::write(base.join(branch).join(&file), format!("{branch} {n}")).unwrap();
+            git(&base.join(branch), &["add", &file]);
+            git(&base.join(branch), &["commit", "-q", "-m", &file]);
+        }
+        git(&repo, &["push", "-q", "origin", branch]);
+    }
+
+    git(&repo, &["merge", "-q", "--squash", "squashed"]);
+    git(&repo, &["commit", "-q", "-m", "Squashed (#1)"]);
+    git(&repo, &["cherry-pick", "main..rebased"]);
+    git(&repo, &["push", "-q", "origin", "main"]);
+
+    let objects = || {
+        let output = Command::new("git")
+            .args(["count-objects"])
+            .current_dir(&repo)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let before = objects();
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &["--format", "json", "clean", "--dry-run"],
+    ));
+    // Detection writes nothing to the object store.
+    assert_eq!(objects(), before);
+    let candidates = report["candidates"].as_array().unwrap();
+    let find = |name: &str| candidates.iter().find(|c| c["branch"] == name);
+
+    let squashed = find("squashed").expect("squash-merged branch is a candidate");
+    assert_eq!(squashed["merged"], true);
+    assert_eq!(squashed["merged_by"], "squash");
+    let rebased = find("rebased").expect("rebase-merged branch is a candidate");
+    assert_eq!(rebased["merged_by"], "rebase");
+    assert!(find("open").is_none());
+
+    let output = run_arvore(&repo, &config, &["clean", "--dry-run"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("squash-merged"), "stdout: {stdout}");
+    assert!(stdout.contains("rebase-merged"), "stdout: {stdout}");
+}
+
+#[test]
+fn all_repos_lists_and_cleans_every_repository() {
+    let (_app_dir, app) = setup_test_repo();
+    let (_lib_dir, lib) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_app_dir.path(), wt_base.path());
+    let _app_origin = setup_origin(&app);
+    let _lib_origin = setup_origin(&lib);
+
+    for repo in [&app, &lib] {
+        let output = run_arvore(repo, &config, &["create", "feature", "--from", "main"]);
+        assert!(output.status.success());
+    }
+
+    let listed = json(&run_arvore(
+        wt_base.path(),
+        &config,
+        &["--format", "json", "ls", "--all-repos"],
+    ));
+    let repos = listed["repos"].as_array().unwrap();
+    assert_eq!(repos.len(), 2);
+    for repo in [&app, &lib] {
+        let name = repo.file_name().unwrap().to_str().unwrap();
+        let group = repos
+            .iter()
+            .find(|r| r["repo"] == name)
+            .unwrap_or_else(|| panic!("{name} is listed"));
+        assert_eq!(
+            group["main_worktree"],
+            repo.canonicalize().unwrap().to_str().unwrap()
+        );
+        let branches: Vec<&str> = group["worktrees"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|w| w["branch"].as_str().unwrap())
+            .collect();
+        assert_eq!(branches, ["main", "feature"]);
+    }
+
+    // Run from inside a worktree that goes away, as the shell wrapper would.
+    let cd_file = _app_dir.path().join("cd");
+    let inside = wt_base.path().join(app.file_name().unwrap()).join("feature");
+    let output = Command::new(arvore_bin())
+        .args(["--config", config.to_str().unwrap()])
+        .args(["--format", "json", "clean", "--all-repos", "--yes"])
+        .current_dir(&inside)
+        .env("ARVORE_CD_FILE", &cd_file)
+        .output()
+        .unwrap();
+    let cleaned = json(&output);
+    assert_eq!(
+        PathBuf::from(std::fs::read_to_string(&cd_file).unwrap()),
+        app.canonicalize().unwrap()
+    );
+    let repos = cleaned["repos"].as_array().unwrap();
+    assert_eq!(repos.len(), 2);
+    for report in repos {
+        let removed = report["removed"].as_array().unwrap();
+        assert_eq!(removed.len(), 1, "report: {report}");
+    }
+    for repo in [&app, &lib] {
+        assert!(
+            !wt_base
+                .path()
+                .join(repo.file_name().unwrap())
+                .join("feature")
+                .exists()
+        );
+    }
+}
+
+#[test]
+fn exec_runs_in_matching_worktrees_and_reports_failures() {
+    let (_dir, repo) = setup_test_repo();
+    let wt_base = tempfile::tempdir().unwrap();
+    let config = write_test_config(_dir.path(), wt_base.path());
+    let base = wt_base.path().join(repo.file_name().unwrap());
+
+    for branch in ["feature/auth", "feature/billing", "bugfix"] {
+        let output = run_arvore(&repo, &config, &["create", branch]);
+        assert!(output.status.success());
+    }
+    std::fs::write(base.join("feature-billing/wip.txt"), "").unwrap();
+
+    let report = json(&run_arvore(
+        &repo,
+        &config,
+        &[
+            "--format",
+            "json",
+            "exec",
+            "--branches",
+            "feature/*",
+            "--parallel",
+            "2",
+            "--",
+            "sh",
+            "-c",
+            "echo $ARVORE_BRANCH",
+        ],
+    ));
+    let results = report["results"].as_array().unwrap();
+    let outputs: Vec<&str> = results
+        .iter()
+        .map(|r| r["stdout"].as_str().unwrap().trim())
+        .collect();
+    assert_eq!(outputs, ["feature/auth", "feature/billing"]);
+    assert!(results.iter().all(|r| r["exit_code"] == 0));
+
+    let output = run_arvore(
+        &repo,
+        &config,
+        &["exec", "--clean", "--", "test", "-e", "README"],
+    );
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("bugfix"), "stdout: {stdout}");
+    assert!(!stdout.contains("feature/billing"), "stdout: {stdout}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("command failed in 2 of 2 worktrees"),
+        "stderr: {stderr}"
+    );
+}